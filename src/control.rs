@@ -0,0 +1,287 @@
+use horny_loader::event;
+use log::{debug, error, info};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::hash_map::RandomState,
+    env, fs,
+    hash::{BuildHasher, Hasher},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use crate::dev::{self, DevSession};
+
+/// written next to `dev.hl.json`, clients send it before any other command
+pub const TOKEN_FILE: &str = "control.hl.token";
+
+/// how often the accept loop checks if the session closed
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// settings that run programs or write files, only the menu may change them
+const LOCAL_KEYS: [&str; 4] = ["hooks", "from_path", "targets", "to_path"];
+
+/// one json line sent by a client, see `Control Server` in the readme
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Command {
+    Auth {
+        token: String,
+    },
+    Status,
+    Update,
+    Acknowledge,
+    Set {
+        key: String,
+        value: serde_json::Value,
+    },
+    Subscribe,
+    Close,
+}
+
+/// the served dev session, kept for `wait_for_close`
+static SESSION: Mutex<Option<DevSession>> = Mutex::new(None);
+
+/// accept control clients in the background on `address`,
+/// `7878`, `127.0.0.1:7878` or `unix:/path/to/socket`
+pub fn serve(address: &str, session: DevSession) -> Result<(), String> {
+    let token = write_token()?;
+    *SESSION.lock().unwrap() = Some(session.clone());
+    if let Some(path) = address.strip_prefix("unix:") {
+        return serve_unix(path, token, session);
+    }
+
+    let address = tcp_address(address)?;
+    let listener =
+        TcpListener::bind(address).map_err(|e| format!("Cannot listen on {}: {}", address, e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Cannot listen on {}: {}", address, e))?;
+    info!("Control server on {}", address);
+    let accept = move || {
+        let (stream, _) = listener.accept()?;
+        stream.set_nonblocking(false)?;
+        Ok((stream.try_clone()?, stream))
+    };
+    accept_clients(accept, token, session, || {});
+    Ok(())
+}
+
+/// accept clients until the session closes, then free the address for the next dev session
+fn accept_clients<R, W>(
+    mut accept: impl FnMut() -> io::Result<(R, W)> + Send + 'static,
+    token: String,
+    session: DevSession,
+    cleanup: impl FnOnce() + Send + 'static,
+) where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || {
+        while !session.closed() {
+            match accept() {
+                Ok((reader, writer)) => client(reader, writer, token.clone(), session.clone()),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+                Err(e) => error!("Control client failed: {}", e),
+            }
+        }
+        drop(accept);
+        cleanup();
+        let mut served = SESSION.lock().unwrap();
+        if served.as_ref().is_some_and(|served| served.closed()) {
+            *served = None;
+        }
+        debug!("Control server stopped");
+    });
+}
+
+#[cfg(unix)]
+fn serve_unix(path: &str, token: String, session: DevSession) -> Result<(), String> {
+    use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+
+    // a socket left by a previous run blocks bind, anything else is not ours to delete
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(format!("Cannot listen on {}: not a socket", path));
+        }
+        fs::remove_file(path).map_err(|e| format!("Cannot remove {}: {}", path, e))?;
+    }
+    let listener =
+        UnixListener::bind(path).map_err(|e| format!("Cannot listen on {}: {}", path, e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Cannot listen on {}: {}", path, e))?;
+    info!("Control server on unix:{}", path);
+    let accept = move || {
+        let (stream, _) = listener.accept()?;
+        stream.set_nonblocking(false)?;
+        Ok((stream.try_clone()?, stream))
+    };
+    let path = path.to_string();
+    accept_clients(accept, token, session, move || {
+        let _ = fs::remove_file(path);
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_: &str, _: String, _: DevSession) -> Result<(), String> {
+    Err("Unix sockets are not supported here, use a localhost port".to_string())
+}
+
+/// keep a headless dev session running for its control clients,
+/// returns once one of them sends `close`
+pub fn wait_for_close() {
+    let Some(session) = SESSION.lock().unwrap().clone() else {
+        return;
+    };
+    if session.closed() {
+        return;
+    }
+    info!("Input closed, dev mode runs until a control client sends `close`");
+    session.wait_closed();
+}
+
+/// a fresh random token each start, readable only by the current user
+fn write_token() -> Result<String, String> {
+    let token: String = (0..2)
+        .map(|_| format!("{:016x}", RandomState::new().build_hasher().finish()))
+        .collect();
+    let path = env::current_dir().unwrap().join(TOKEN_FILE);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(token.as_bytes()))
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(token)
+}
+
+/// browsers can reach localhost too, their requests start with an http request line
+fn is_http(line: &str) -> bool {
+    line.split_whitespace()
+        .nth(2)
+        .is_some_and(|version| version.starts_with("HTTP/"))
+}
+
+/// only loopback addresses, clients also need the token
+fn tcp_address(address: &str) -> Result<SocketAddr, String> {
+    let address = if address.parse::<u16>().is_ok() {
+        format!("127.0.0.1:{}", address)
+    } else {
+        address.to_string()
+    };
+    let address = address
+        .to_socket_addrs()
+        .map_err(|e| format!("Invalid control address `{}`: {}", address, e))?
+        .next()
+        .ok_or(format!("Invalid control address `{}`", address))?;
+    if !address.ip().is_loopback() {
+        return Err(format!("Control address {} is not localhost", address));
+    }
+    Ok(address)
+}
+
+fn client(
+    reader: impl Read + Send + 'static,
+    mut writer: impl Write + Send + 'static,
+    token: String,
+    session: DevSession,
+) {
+    thread::spawn(move || {
+        debug!("Control client connected");
+        let mut authorized = false;
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if is_http(&line) {
+                debug!("Control client sent http, closed");
+                break;
+            }
+
+            //anything unexpected closes the connection
+            let (response, close) = match serde_json::from_str::<Command>(&line) {
+                Err(e) => (error(format!("Invalid command: {}", e)), true),
+                Ok(Command::Auth { token: sent }) if sent == token => {
+                    authorized = true;
+                    (json!({ "ok": true }), false)
+                }
+                Ok(Command::Auth { .. }) => (error("Wrong token".to_string()), true),
+                Ok(_) if !authorized => (
+                    error(format!("Send the token of `{}` first", TOKEN_FILE)),
+                    true,
+                ),
+                Ok(Command::Subscribe) => {
+                    subscribe(writer);
+                    return;
+                }
+                Ok(command) => (run(command, &session), false),
+            };
+            //a closed session has nothing left to control
+            if writeln!(writer, "{}", response).is_err() || close || session.closed() {
+                break;
+            }
+        }
+        debug!("Control client disconnected");
+    });
+}
+
+fn run(command: Command, session: &DevSession) -> serde_json::Value {
+    debug!("Control command {:?}", command);
+    match command {
+        Command::Status => json!({ "ok": true, "status": session.status() }),
+        Command::Update => {
+            session.update();
+            json!({ "ok": true })
+        }
+        Command::Acknowledge => {
+            session.acknowledge();
+            json!({ "ok": true })
+        }
+        Command::Close => {
+            session.close();
+            json!({ "ok": true })
+        }
+        Command::Set { key, .. } if LOCAL_KEYS.contains(&dev::setting_key(&key).as_str()) => {
+            error(format!("`{}` can only be changed in the menu", key))
+        }
+        Command::Set { key, value } => {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            match session.set(&key, &value) {
+                Ok(()) => json!({ "ok": true }),
+                Err(e) => error(e),
+            }
+        }
+        Command::Auth { .. } | Command::Subscribe => unreachable!(),
+    }
+}
+
+fn error(error: String) -> serde_json::Value {
+    json!({ "ok": false, "error": error })
+}
+
+/// forward the event stream until the client goes away
+fn subscribe(mut writer: impl Write) {
+    let events = event::subscribe();
+    if writeln!(writer, "{}", json!({ "ok": true })).is_err() {
+        return;
+    }
+    for line in events {
+        if writeln!(writer, "{}", line).is_err() {
+            break;
+        }
+    }
+    debug!("Control subscriber disconnected");
+}
@@ -0,0 +1,955 @@
+use image::{
+    imageops::{self, FilterType},
+    Pixel, RgbaImage,
+};
+use image_dds::{ImageFormat, Mipmaps, Quality};
+use log::{debug, error, info, trace};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::{self, Display},
+    fs,
+    hash::{Hash, Hasher},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread,
+    time::SystemTime,
+};
+
+use crate::event::{self, Event, TextureStatus};
+
+/// formats which can be encoded by image_dds
+pub const ENCODE_FORMATS: [ImageFormat; 14] = [
+    ImageFormat::BC7Srgb,
+    ImageFormat::BC7Unorm,
+    ImageFormat::BC1Srgb,
+    ImageFormat::BC1Unorm,
+    ImageFormat::BC3Srgb,
+    ImageFormat::BC3Unorm,
+    ImageFormat::BC4Unorm,
+    ImageFormat::BC5Unorm,
+    ImageFormat::BC6Ufloat,
+    ImageFormat::R8Unorm,
+    ImageFormat::R8G8B8A8Srgb,
+    ImageFormat::R8G8B8A8Unorm,
+    ImageFormat::B8G8R8A8Srgb,
+    ImageFormat::B8G8R8A8Unorm,
+];
+
+/// encodable format by name, e.g. `BC7Srgb`
+pub fn encode_format(name: &str) -> Option<ImageFormat> {
+    ENCODE_FORMATS
+        .into_iter()
+        .find(|format| format.to_string() == name)
+}
+
+/// how a single texture is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DDSEncoding {
+    pub format: ImageFormat,
+    pub quality: Quality,
+    /// generate the full mip chain
+    pub mipmaps: bool,
+    /// downscale if width or height is larger
+    #[serde(default)]
+    pub max_size: Option<u32>,
+    #[serde(default)]
+    pub filter: ResizeFilter,
+    /// resize to the nearest power of two
+    #[serde(default)]
+    pub power_of_two: bool,
+}
+
+impl DDSEncoding {
+    pub fn new(format: ImageFormat) -> DDSEncoding {
+        DDSEncoding {
+            format,
+            quality: Quality::Slow,
+            mipmaps: false,
+            max_size: None,
+            filter: ResizeFilter::default(),
+            power_of_two: false,
+        }
+    }
+    /// size after applying `max_size` and `power_of_two`
+    pub fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (mut width, mut height) = (width, height);
+        if let Some(max_size) = self.max_size {
+            let larger = width.max(height);
+            if larger > max_size {
+                let scale = max_size as f64 / larger as f64;
+                width = ((width as f64 * scale).round() as u32).max(1);
+                height = ((height as f64 * scale).round() as u32).max(1);
+            }
+        }
+        if self.power_of_two {
+            let nearest = |value: u32| {
+                let upper = value.next_power_of_two();
+                let lower = (upper / 2).max(1);
+                let nearest = if value - lower < upper - value {
+                    lower
+                } else {
+                    upper
+                };
+                match self.max_size {
+                    Some(max_size) if nearest > max_size => lower,
+                    _ => nearest,
+                }
+            };
+            width = nearest(width);
+            height = nearest(height);
+        }
+        (width, height)
+    }
+    pub fn resize(&self, image: RgbaImage) -> RgbaImage {
+        let (width, height) = self.size(image.width(), image.height());
+        if (width, height) == image.dimensions() {
+            image
+        } else {
+            imageops::resize(&image, width, height, self.filter.into())
+        }
+    }
+    pub fn mipmaps(&self) -> Mipmaps {
+        if self.mipmaps {
+            Mipmaps::GeneratedAutomatic
+        } else {
+            Mipmaps::Disabled
+        }
+    }
+}
+
+impl Display for DDSEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:?}", self.format, self.quality)?;
+        if self.mipmaps {
+            write!(f, " Mipmaps")?;
+        }
+        if let Some(max_size) = self.max_size {
+            write!(f, " Max{}", max_size)?;
+        }
+        if self.power_of_two {
+            write!(f, " PowerOfTwo")?;
+        }
+        if self.max_size.is_some() || self.power_of_two {
+            write!(f, " {:?}", self.filter)?;
+        }
+        Ok(())
+    }
+}
+
+/// filter used when resizing before encoding
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    #[default]
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    pub const ALL: [ResizeFilter; 5] = [
+        ResizeFilter::Nearest,
+        ResizeFilter::Triangle,
+        ResizeFilter::CatmullRom,
+        ResizeFilter::Gaussian,
+        ResizeFilter::Lanczos3,
+    ];
+}
+
+impl Display for ResizeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// encoding chosen by the layout name at the end of the file name,
+/// e.g. `CharBodyLightMap.png` uses the `LightMap` encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DDSPolicy {
+    pub default: DDSEncoding,
+    #[serde(default)]
+    pub layouts: BTreeMap<String, DDSEncoding>,
+}
+
+impl DDSPolicy {
+    pub fn encoding(&self, stem: &str) -> DDSEncoding {
+        self.layouts
+            .iter()
+            .filter(|(layout, _)| stem.ends_with(layout.as_str()))
+            .max_by_key(|(layout, _)| layout.len())
+            .map(|(_, encoding)| *encoding)
+            .unwrap_or(self.default)
+    }
+}
+
+impl Default for DDSPolicy {
+    fn default() -> Self {
+        let layouts = [
+            ("Diffuse", ImageFormat::BC7Srgb),
+            ("LightMap", ImageFormat::BC7Unorm),
+            ("NormalMap", ImageFormat::BC7Unorm),
+        ]
+        .into_iter()
+        .map(|(layout, format)| (layout.to_string(), DDSEncoding::new(format)))
+        .collect();
+        DDSPolicy {
+            default: DDSEncoding::new(ImageFormat::BC7Srgb),
+            layouts,
+        }
+    }
+}
+
+impl Display for DDSPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Default: {}", self.default)?;
+        for (layout, encoding) in &self.layouts {
+            write!(f, ", {}: {}", layout, encoding)?;
+        }
+        Ok(())
+    }
+}
+
+/// decode the first mip level of a dds file to png, returns the format of the dds
+pub fn png_from_dds(dds_path: &Path, png_path: &Path) -> Result<ImageFormat, String> {
+    let file = fs::File::open(dds_path).map_err(|e| format!("Cannot read file: {}", e))?;
+    let dds = image_dds::ddsfile::Dds::read(std::io::BufReader::new(file))
+        .map_err(|e| format!("Invalid dds: {}", e))?;
+    let format = image_dds::dds_image_format(&dds).ok_or("Unsupported dds format".to_string())?;
+    let image = image_dds::image_from_dds(&dds, 0)
+        .map_err(|e| format!("Cannot decode {}: {}", format, e))?;
+    image
+        .save_with_format(png_path, image::ImageFormat::Png)
+        .map_err(|e| format!("Cannot write {}: {}", png_path.display(), e))?;
+    Ok(format)
+}
+
+/// channel packing recipes of a workspace, `{"CharBodyLightMap": {"r": "metal.png", ...}}`
+pub const PACK_FILE: &str = "textures.hl.json";
+
+/// builds one texture from separate channel maps in `textures`,
+/// `mask.png` reads a grayscale map, `mask.png:g` reads a single channel
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackRecipe {
+    pub r: Option<String>,
+    pub g: Option<String>,
+    pub b: Option<String>,
+    pub a: Option<String>,
+}
+
+impl PackRecipe {
+    /// file and channel of every input, unused channels are filled with the default value
+    fn channels(&self) -> [(Option<PackInput<'_>>, u8); 4] {
+        [
+            (parse_input(&self.r), 0),
+            (parse_input(&self.g), 0),
+            (parse_input(&self.b), 0),
+            (parse_input(&self.a), 255),
+        ]
+    }
+    fn inputs(&self) -> Vec<&str> {
+        let mut inputs = vec![];
+        for (input, _) in self.channels() {
+            if let Some((file, _)) = input {
+                if !inputs.contains(&file) {
+                    inputs.push(file);
+                }
+            }
+        }
+        inputs
+    }
+    fn pack(&self, sources: &[Vec<u8>]) -> Result<RgbaImage, String> {
+        let inputs = self.inputs();
+        let images = inputs
+            .iter()
+            .zip(sources)
+            .map(|(file, source)| {
+                image::load_from_memory(source)
+                    .map(|image| image.to_rgba8())
+                    .map_err(|e| format!("Unsupported image {}: {}", file, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let Some(first) = images.first() else {
+            return Err("Recipe has no input".to_string());
+        };
+        let (width, height) = first.dimensions();
+        for (file, image) in inputs.iter().zip(&images) {
+            if image.dimensions() != (width, height) {
+                return Err(format!(
+                    "{} is {}x{}, but other inputs are {}x{}",
+                    file,
+                    image.width(),
+                    image.height(),
+                    width,
+                    height
+                ));
+            }
+        }
+
+        let channels = self.channels();
+        Ok(RgbaImage::from_fn(width, height, |x, y| {
+            let mut pixel = [0_u8; 4];
+            for (i, (input, default)) in channels.iter().enumerate() {
+                pixel[i] = match input {
+                    Some((file, channel)) => {
+                        let index = inputs.iter().position(|input| input == file).unwrap();
+                        let rgba = images[index].get_pixel(x, y).0;
+                        match channel {
+                            Some(channel) => rgba[*channel],
+                            None => image::Rgba(rgba).to_luma().0[0],
+                        }
+                    }
+                    None => *default,
+                };
+            }
+            image::Rgba(pixel)
+        }))
+    }
+}
+
+/// file and channel of a recipe input, no channel means grayscale
+type PackInput<'a> = (&'a str, Option<usize>);
+
+fn parse_input(input: &Option<String>) -> Option<PackInput<'_>> {
+    input.as_deref().map(|input| match input.rsplit_once(':') {
+        Some((file, "r")) => (file, Some(0)),
+        Some((file, "g")) => (file, Some(1)),
+        Some((file, "b")) => (file, Some(2)),
+        Some((file, "a")) => (file, Some(3)),
+        _ => (input, None),
+    })
+}
+
+fn load_recipes(path: &Path) -> Result<BTreeMap<String, PackRecipe>, String> {
+    let pack_path = path.join(PACK_FILE);
+    if !pack_path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let json = fs::read_to_string(&pack_path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid {}: {}", PACK_FILE, e))
+}
+
+const DDS_CACHE: &str = ".hl_cache";
+
+/// converted textures of a workspace, kept in `.hl_cache` between dev sessions
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DDSCache {
+    entries: HashMap<String, DDSCacheEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct DDSCacheEntry {
+    /// hash of the source content and the encoding
+    key: u64,
+    output_len: u64,
+}
+
+impl DDSCache {
+    fn load(path: &Path) -> DDSCache {
+        fs::read(path.join(DDS_CACHE))
+            .ok()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok())
+            .unwrap_or_default()
+    }
+    fn save(&self, path: &Path) {
+        let bytes = bincode::serialize(self).unwrap();
+        fs::write(path.join(DDS_CACHE), bytes)
+            .unwrap_or_else(|e| error!("Cannot write {}: {}", DDS_CACHE, e));
+    }
+    /// output is up to date if it was written from the same source and encoding
+    fn is_fresh(&self, output: &Path, name: &str, key: u64) -> bool {
+        let Some(entry) = self.entries.get(name) else {
+            return false;
+        };
+        let output_len = fs::metadata(output).map(|meta| meta.len()).ok();
+        entry.key == key && output_len == Some(entry.output_len)
+    }
+    fn key(sources: &[Vec<u8>], encoding: &DDSEncoding, recipe: Option<&PackRecipe>) -> u64 {
+        // only used to detect changes, a different std hasher just causes a reconversion
+        let mut hasher = DefaultHasher::new();
+        sources.hash(&mut hasher);
+        bincode::serialize(encoding).unwrap().hash(&mut hasher);
+        bincode::serialize(&recipe).unwrap().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// files written by HornyLoader with their modified time,
+/// so the watcher can tell them from changes made by the user
+#[derive(Debug, Clone, Default)]
+pub struct WriteLog(Arc<Mutex<HashMap<PathBuf, SystemTime>>>);
+
+impl WriteLog {
+    pub fn insert(&self, path: &Path, metadata: &fs::Metadata) {
+        if let Ok(modified) = metadata.modified() {
+            self.0.lock().unwrap().insert(path.to_path_buf(), modified);
+        }
+    }
+    /// the file is unchanged since HornyLoader wrote it
+    pub fn contains(&self, path: &Path) -> bool {
+        let Some(modified) = self.0.lock().unwrap().get(path).copied() else {
+            return false;
+        };
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|current| current == modified)
+    }
+}
+
+/// progress of a texture in the conversion pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DDSEvent {
+    Started(PathBuf),
+    Finished(PathBuf),
+    /// output is already up to date
+    Skipped(PathBuf),
+    /// a newer version of the file was queued
+    Cancelled(PathBuf),
+    Failed(PathBuf, String),
+}
+
+impl DDSEvent {
+    pub fn path(&self) -> &Path {
+        match self {
+            DDSEvent::Started(path)
+            | DDSEvent::Finished(path)
+            | DDSEvent::Skipped(path)
+            | DDSEvent::Cancelled(path)
+            | DDSEvent::Failed(path, _) => path,
+        }
+    }
+}
+
+struct DDSJob {
+    path: PathBuf,
+    name: String,
+    recipe: Option<PackRecipe>,
+    output_path: PathBuf,
+    encoding: DDSEncoding,
+    version: u64,
+}
+
+#[derive(Default)]
+struct DDSQueue {
+    jobs: VecDeque<DDSJob>,
+    /// latest version of every queued file
+    latest: HashMap<PathBuf, u64>,
+    closed: bool,
+}
+
+/// called by the conversion pool after each progress event
+type Notify = Arc<Mutex<Option<Box<dyn Fn() + Send>>>>;
+
+/// snapshot of the conversion queue, shared with the terminal ui
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DDSStatus {
+    pub queued: BTreeSet<PathBuf>,
+    pub running: BTreeSet<PathBuf>,
+    pub done: usize,
+    pub total: usize,
+    pub errors: BTreeMap<PathBuf, String>,
+}
+
+/// converts textures on a fixed number of worker threads
+pub struct DDSParser {
+    path: PathBuf,
+    policy: DDSPolicy,
+    queue: Arc<(Mutex<DDSQueue>, Condvar)>,
+    cache: Arc<Mutex<DDSCache>>,
+    write_log: WriteLog,
+    events: mpsc::Receiver<(DDSEvent, u64)>,
+    /// files waiting for a result, with the version to wait for
+    pending: HashMap<PathBuf, u64>,
+    version: u64,
+    done: usize,
+    total: usize,
+    errors: BTreeMap<PathBuf, String>,
+    recipes: BTreeMap<String, PackRecipe>,
+    /// only textures with these names are converted, if set
+    only: Option<HashSet<String>>,
+    /// format hints of hash.json, override the policy
+    formats: HashMap<String, ImageFormat>,
+    running: BTreeSet<PathBuf>,
+    status: Arc<Mutex<DDSStatus>>,
+    notify: Notify,
+}
+
+impl DDSParser {
+    /// converter for the `textures` folder of the workspace `path`, into `path/assets`
+    pub fn new(path: PathBuf, policy: DDSPolicy) -> DDSParser {
+        let queue = Arc::new((Mutex::new(DDSQueue::default()), Condvar::new()));
+        let cache = Arc::new(Mutex::new(DDSCache::load(&path)));
+        let write_log = WriteLog::default();
+        let (tx, events) = mpsc::channel();
+        let notify = Notify::default();
+
+        // leave some cores for blender and the game
+        let workers = thread::available_parallelism()
+            .map(|n| n.get() / 2)
+            .unwrap_or(1)
+            .max(1);
+        for _ in 0..workers {
+            let queue = queue.clone();
+            let cache = cache.clone();
+            let write_log = write_log.clone();
+            let tx = tx.clone();
+            let notify = notify.clone();
+            thread::spawn(move || dds_worker(queue, cache, write_log, tx, notify));
+        }
+
+        let mut parser = DDSParser {
+            path,
+            policy,
+            queue,
+            cache,
+            write_log,
+            events,
+            pending: HashMap::new(),
+            version: 0,
+            done: 0,
+            total: 0,
+            errors: BTreeMap::new(),
+            recipes: BTreeMap::new(),
+            only: None,
+            formats: HashMap::new(),
+            running: BTreeSet::new(),
+            status: Arc::default(),
+            notify,
+        };
+        parser.load_recipes();
+        parser
+    }
+    /// queue a texture, replaces the queued job of the same file,
+    /// inputs of a channel packing recipe queue the recipe instead
+    pub fn parse(&mut self, path: PathBuf) {
+        let pack_path = self.path.join(PACK_FILE);
+        if path == pack_path {
+            self.load_recipes();
+            let names: Vec<String> = self.recipes.keys().cloned().collect();
+            for name in names {
+                self.pack(name);
+            }
+            return;
+        }
+
+        let textures = self.path.join("textures");
+        let names: Vec<String> = self
+            .recipes
+            .iter()
+            .filter(|(_, recipe)| {
+                recipe
+                    .inputs()
+                    .iter()
+                    .any(|file| textures.join(file) == path)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        if !names.is_empty() {
+            for name in names {
+                self.pack(name);
+            }
+            return;
+        }
+
+        if !path.is_file() {
+            // removed file is no longer an error
+            self.errors.remove(&path);
+            self.update_status();
+            return;
+        }
+
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        self.queue(path, name, None);
+    }
+    fn pack(&mut self, name: String) {
+        let recipe = self.recipes[&name].clone();
+        let path = self.path.join("textures").join(&name);
+        self.queue(path, name, Some(recipe));
+    }
+    /// limit conversion to the given texture names, `None` converts everything
+    pub fn only(&mut self, names: Option<HashSet<String>>) {
+        self.only = names;
+    }
+    /// formats by texture name, used instead of the policy format
+    pub fn formats(&mut self, formats: HashMap<String, ImageFormat>) {
+        self.formats = formats;
+    }
+    fn queue(&mut self, path: PathBuf, name: String, recipe: Option<PackRecipe>) {
+        if let Some(only) = &self.only {
+            if !only.contains(&name) {
+                info!("Skip dds file ({}), not used by hash.json", path.display());
+                return;
+            }
+        }
+
+        let mut encoding = self.policy.encoding(&name);
+        if let Some(format) = self.formats.get(&name) {
+            encoding.format = *format;
+        }
+
+        self.version += 1;
+        let job = DDSJob {
+            path: path.clone(),
+            encoding,
+            name,
+            recipe,
+            output_path: self.path.clone(),
+            version: self.version,
+        };
+
+        let (queue, condvar) = &*self.queue;
+        let mut queue = queue.lock().unwrap();
+        queue.jobs.retain(|job| job.path != path);
+        queue.latest.insert(path.clone(), self.version);
+        queue.jobs.push_back(job);
+        condvar.notify_one();
+
+        if self.pending.insert(path, self.version).is_none() {
+            self.total += 1;
+        }
+        self.update_status();
+    }
+    /// print the progress of converted textures without blocking
+    pub fn poll(&mut self) -> Vec<DDSEvent> {
+        let events: Vec<_> = self.events.try_iter().collect();
+        events
+            .into_iter()
+            .map(|(event, version)| self.handle(event, version))
+            .collect()
+    }
+    /// nothing is queued or converting, never blocks
+    pub fn idle(&mut self) -> bool {
+        self.poll();
+        self.pending.is_empty()
+    }
+    /// wake an event loop from the conversion pool, it should `poll` or check `idle` then
+    pub fn notify(&self, notify: impl Fn() + Send + 'static) {
+        *self.notify.lock().unwrap() = Some(Box::new(notify));
+    }
+    fn handle(&mut self, event: DDSEvent, version: u64) -> DDSEvent {
+        let progress = format!("[{}/{}]", self.done + 1, self.total);
+        match &event {
+            DDSEvent::Started(path) => {
+                info!("{} Parse dds file ({})", progress, path.display())
+            }
+            DDSEvent::Finished(path) => {
+                info!("{} Finished dds file ({})", progress, path.display())
+            }
+            DDSEvent::Skipped(path) => {
+                info!(
+                    "{} Skip dds file ({}), up to date",
+                    progress,
+                    path.display()
+                )
+            }
+            DDSEvent::Cancelled(path) => {
+                info!("Cancel dds file ({}), newer version queued", path.display())
+            }
+            DDSEvent::Failed(path, error) => {
+                error!(
+                    "{} Failed dds file ({}): {}",
+                    progress,
+                    path.display(),
+                    error
+                )
+            }
+        }
+
+        let status = match &event {
+            DDSEvent::Started(_) => None,
+            DDSEvent::Finished(_) => Some(TextureStatus::Converted),
+            DDSEvent::Skipped(_) => Some(TextureStatus::Skipped),
+            DDSEvent::Cancelled(_) => Some(TextureStatus::Cancelled),
+            DDSEvent::Failed(..) => Some(TextureStatus::Failed),
+        };
+        if let Some(status) = status {
+            let error = match &event {
+                DDSEvent::Failed(_, error) => Some(error.clone()),
+                _ => None,
+            };
+            event::emit(Event::TextureConverted {
+                path: event.path().to_path_buf(),
+                status,
+                error,
+            });
+        }
+
+        match &event {
+            DDSEvent::Started(path) => {
+                self.running.insert(path.clone());
+            }
+            DDSEvent::Cancelled(path) => {
+                self.running.remove(path);
+            }
+            DDSEvent::Finished(path) | DDSEvent::Skipped(path) => {
+                self.running.remove(path);
+                self.errors.remove(path);
+            }
+            DDSEvent::Failed(path, error) => {
+                self.running.remove(path);
+                self.errors.insert(path.clone(), error.clone());
+            }
+        }
+
+        let waiting = self.pending.get(event.path()) == Some(&version);
+        if waiting && !matches!(event, DDSEvent::Started(_)) {
+            self.pending.remove(event.path());
+            self.done += 1;
+        }
+        if self.pending.is_empty() {
+            self.done = 0;
+            self.total = 0;
+        }
+        self.update_status();
+        event
+    }
+    fn update_status(&self) {
+        let mut status = self.status.lock().unwrap();
+        status.queued = self
+            .pending
+            .keys()
+            .filter(|path| !self.running.contains(*path))
+            .cloned()
+            .collect();
+        status.running = self.running.clone();
+        status.done = self.done;
+        status.total = self.total;
+        status.errors = self.errors.clone();
+    }
+    /// live queue state, updated while converting
+    pub fn status(&self) -> Arc<Mutex<DDSStatus>> {
+        self.status.clone()
+    }
+    /// files written by the conversion pool
+    pub fn write_log(&self) -> WriteLog {
+        self.write_log.clone()
+    }
+    /// failed conversions since the last acknowledge
+    pub fn errors(&self) -> &BTreeMap<PathBuf, String> {
+        &self.errors
+    }
+    /// forget the failed conversions
+    pub fn acknowledge(&mut self) {
+        self.errors.clear();
+        self.update_status();
+    }
+    fn load_recipes(&mut self) {
+        let pack_path = self.path.join(PACK_FILE);
+        match load_recipes(&self.path) {
+            Ok(recipes) => {
+                self.errors.remove(&pack_path);
+                self.recipes = recipes;
+            }
+            Err(error) => {
+                error!("{}", error);
+                self.errors.insert(pack_path, error);
+            }
+        }
+        self.update_status();
+    }
+    /// switch workspace or policy, queued jobs keep their settings
+    pub fn reload(&mut self, path: PathBuf, policy: DDSPolicy) {
+        if path != self.path {
+            *self.cache.lock().unwrap() = DDSCache::load(&path);
+            self.path = path;
+            self.load_recipes();
+        }
+        self.policy = policy;
+    }
+}
+
+impl Drop for DDSParser {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.queue;
+        queue.lock().unwrap().closed = true;
+        condvar.notify_all();
+    }
+}
+
+fn dds_worker(
+    queue: Arc<(Mutex<DDSQueue>, Condvar)>,
+    cache: Arc<Mutex<DDSCache>>,
+    write_log: WriteLog,
+    tx: mpsc::Sender<(DDSEvent, u64)>,
+    notify: Notify,
+) {
+    let is_latest = |job: &DDSJob| {
+        let queue = queue.0.lock().unwrap();
+        queue.latest.get(&job.path) == Some(&job.version)
+    };
+
+    loop {
+        let job = {
+            let (queue, condvar) = &*queue;
+            let mut queue = queue.lock().unwrap();
+            loop {
+                if queue.closed {
+                    return;
+                }
+                if let Some(job) = queue.jobs.pop_front() {
+                    break job;
+                }
+                queue = condvar.wait(queue).unwrap();
+            }
+        };
+
+        let send = |event: DDSEvent| {
+            let sent = tx.send((event, job.version)).is_ok();
+            if let Some(notify) = &*notify.lock().unwrap() {
+                notify();
+            }
+            sent
+        };
+        if !send(DDSEvent::Started(job.path.clone())) {
+            return;
+        }
+
+        let event = convert(&job, &cache, &write_log, || is_latest(&job))
+            .unwrap_or_else(|error| DDSEvent::Failed(job.path.clone(), error));
+        send(event);
+    }
+}
+
+fn convert(
+    job: &DDSJob,
+    cache: &Mutex<DDSCache>,
+    write_log: &WriteLog,
+    is_latest: impl Fn() -> bool,
+) -> Result<DDSEvent, String> {
+    let dds_name = job.name.clone() + ".dds";
+    let dds_path = job.output_path.join("assets").join(&dds_name);
+    let files = match &job.recipe {
+        Some(recipe) => {
+            let textures = job.output_path.join("textures");
+            recipe
+                .inputs()
+                .iter()
+                .map(|file| textures.join(file))
+                .collect()
+        }
+        None => vec![job.path.clone()],
+    };
+    let sources = files
+        .iter()
+        .map(|file| fs::read(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = DDSCache::key(&sources, &job.encoding, job.recipe.as_ref());
+    if cache.lock().unwrap().is_fresh(&dds_path, &dds_name, key) {
+        trace!("Cache hit {} ({:x})", dds_name, key);
+        return Ok(DDSEvent::Skipped(job.path.clone()));
+    }
+
+    let image = match &job.recipe {
+        Some(recipe) => recipe.pack(&sources)?,
+        None => image::load_from_memory(&sources[0])
+            .map_err(|e| format!("Unsupported image: {}", e))?
+            .to_rgba8(),
+    };
+    debug!(
+        "Encode {} {}x{} as {}",
+        dds_name,
+        image.width(),
+        image.height(),
+        job.encoding
+    );
+    let image = job.encoding.resize(image);
+    let format = job.encoding.format;
+    let compressed = format.to_string().starts_with("BC");
+    if compressed && (image.width() | image.height()) & 3 != 0 {
+        return Err(format!(
+            "{}x{} is not a multiple of 4, which {} needs",
+            image.width(),
+            image.height(),
+            format
+        ));
+    }
+    let dds =
+        image_dds::dds_from_image(&image, format, job.encoding.quality, job.encoding.mipmaps())
+            .map_err(|e| format!("Cannot encode {}: {}", format, e))?;
+
+    // encoding takes a while, the file may have changed meanwhile
+    if !is_latest() {
+        return Ok(DDSEvent::Cancelled(job.path.clone()));
+    }
+
+    //write under a name the watcher does not route, then log it before it appears as the dds,
+    //so the rename event is already known as our own write
+    let write_error = |e: &dyn Display| format!("Cannot write {}: {}", dds_path.display(), e);
+    let mut temp_name = dds_path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".hl_tmp");
+    let temp_path = dds_path.with_file_name(temp_name);
+    let mut writer =
+        std::io::BufWriter::new(fs::File::create(&temp_path).map_err(|e| write_error(&e))?);
+    dds.write(&mut writer).map_err(|e| write_error(&e))?;
+    writer.flush().map_err(|e| write_error(&e))?;
+    drop(writer);
+
+    let metadata = fs::metadata(&temp_path).map_err(|e| write_error(&e))?;
+    write_log.insert(&dds_path, &metadata);
+    fs::rename(&temp_path, &dds_path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        write_error(&e)
+    })?;
+    event::emit(Event::FileWritten {
+        path: dds_path.clone(),
+    });
+    let output_len = metadata.len();
+    let mut cache = cache.lock().unwrap();
+    cache
+        .entries
+        .insert(dds_name, DDSCacheEntry { key, output_len });
+    cache.save(&job.output_path);
+    Ok(DDSEvent::Finished(job.path.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoding(max_size: Option<u32>, power_of_two: bool) -> DDSEncoding {
+        DDSEncoding {
+            max_size,
+            power_of_two,
+            ..DDSEncoding::new(ImageFormat::BC7Srgb)
+        }
+    }
+
+    #[test]
+    fn max_size_keeps_aspect_ratio() {
+        let encoding = encoding(Some(1024), false);
+        assert_eq!(encoding.size(4096, 2048), (1024, 512));
+        assert_eq!(encoding.size(512, 300), (512, 300));
+        assert_eq!(encoding.size(4096, 1), (1024, 1));
+    }
+
+    #[test]
+    fn power_of_two_rounds_to_nearest() {
+        let encoding = encoding(None, true);
+        assert_eq!(encoding.size(300, 200), (256, 256));
+        assert_eq!(encoding.size(256, 1), (256, 1));
+        //halfway goes up
+        assert_eq!(encoding.size(384, 96), (512, 128));
+    }
+
+    #[test]
+    fn power_of_two_stays_within_max_size() {
+        let encoding = encoding(Some(1000), true);
+        assert_eq!(encoding.size(1000, 1000), (512, 512));
+        assert_eq!(encoding.size(2000, 1000), (512, 512));
+    }
+}
@@ -1,336 +1,999 @@
-use notify::{ReadDirectoryChangesWatcher, Watcher};
-use rfd::FileDialog;
+use glob::Pattern;
+use horny_loader::{
+    dds::{
+        DDSEncoding, DDSParser, DDSPolicy, DDSStatus, ResizeFilter, WriteLog, ENCODE_FORMATS,
+        PACK_FILE,
+    },
+    event::{self, Event},
+    hook::{self, BuildResult},
+    load::{self, TextureFilter},
+    sync,
+};
+use image_dds::Quality;
+use log::{debug, error, info, trace, warn};
+use notify::Watcher;
+use serde::{Deserialize, Serialize};
 use std::{
-    borrow::BorrowMut,
-    collections::HashSet,
-    env, fs, mem,
-    path::PathBuf,
-    sync::{mpsc, Arc, Mutex},
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Condvar, Mutex,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use crate::{list_and_choose, load, read_input};
+use crate::{control, list_and_choose, read_folder, read_input, tui};
+
+const DEV_PROJECT: &str = "dev.hl.json";
 
 pub fn main() -> Result<(), String> {
     let (tx, rx) = mpsc::channel::<DevThreadMessage>();
-    let mut config = DevConfig::new();
+    let project_path = env::current_dir().unwrap().join(DEV_PROJECT);
+    let mut project = DevProject::load(&project_path)?;
+    //flags and env vars only change this run, `saved` is what menu edits write back
+    let saved = project.clone();
+    for (key, value) in overrides()? {
+        if key == "profile" {
+            let config = project.config();
+            project.profiles.entry(value.clone()).or_insert(config);
+            project.current = value;
+        } else {
+            let config = project.config().set(&key, &value)?;
+            project.set_config(config);
+        }
+    }
+    let config = project.config();
+    info!("Profile: {}", project.current);
 
+    let thread_config = config.clone();
+    let thread_tx = tx.clone();
+    let mut dds_parser = DDSParser::new(config.from_path.get(), config.dds_policy.get());
+    let dds_status = dds_parser.status();
+    let notify_tx = tx.clone();
+    dds_parser.notify(move || {
+        let _ = notify_tx.send(DevThreadMessage::Converted);
+    });
+    let last_build = LastBuild::default();
+    let thread_last_build = last_build.clone();
+    let mut file_watcher =
+        FileWatcher::new(config.poll_watch.get(), tx.clone(), dds_parser.write_log())?;
     thread::spawn(move || {
-        let mut config = DevConfig::new();
-        let mut file_watcher = FileWatcher::new();
-        let mut dds_parser = DDSParser::new(config.from_path.get());
+        let tx = thread_tx;
+        let mut config = thread_config;
+        let last_build = thread_last_build;
+        file_watcher.set_ignore(&config.ignore.get());
 
-        let mut need_build = 0_usize;
+        let mut debouncer = Debouncer::new(
+            Duration::from_millis(config.quiet_period.get()),
+            Duration::from_millis(config.max_wait.get()),
+        );
+        //a requested build waits here until the dds queue is empty
+        let mut build_pending = false;
         loop {
             let mut force_build = false;
             let mut parse_list = HashSet::new();
 
-            //if config updated
-            if let Some(event) = rx.try_recv().ok() {
-                match event {
-                    DevThreadMessage::Config(new_config) => {
-                        dds_parser.reload(new_config.from_path.get());
+            //wait for a message, or until the debounced rebuild is due
+            let message = match debouncer.deadline() {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    rx.recv_timeout(timeout)
+                }
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match message {
+                Ok(DevThreadMessage::Config(new_config)) => {
+                    dds_parser.reload(new_config.from_path.get(), new_config.dds_policy.get());
 
-                        if new_config.hot_reload.get() {
-                            file_watcher.watch(new_config.from_path.get());
-                        } else {
-                            file_watcher.close()
+                    if new_config.poll_watch.get() != config.poll_watch.get() {
+                        //keep the current watcher if the new one cannot start
+                        match FileWatcher::new(
+                            new_config.poll_watch.get(),
+                            tx.clone(),
+                            dds_parser.write_log(),
+                        ) {
+                            Ok(new_watcher) => {
+                                file_watcher.close();
+                                file_watcher = new_watcher;
+                            }
+                            Err(e) => error!("{}", e),
                         }
-
-                        config = new_config
                     }
-                    DevThreadMessage::Update => {
-                        let textures_dir = config.from_path.get().join("textures");
-                        if textures_dir.is_dir() {
-                            let textures = textures_dir
-                                .read_dir()
-                                .unwrap()
-                                .filter_map(|e| e.ok().and_then(|e| Some(e.path())));
-                            parse_list.extend(textures);
-                        };
-                        force_build = true
-                    }
-                    DevThreadMessage::Close => {
+                    file_watcher.set_ignore(&new_config.ignore.get());
+
+                    if new_config.hot_reload.get() {
+                        file_watcher.watch(new_config.from_path.get());
+                    } else {
                         file_watcher.close();
-                        return;
+                        debouncer.reset();
                     }
+
+                    debouncer.set(
+                        Duration::from_millis(new_config.quiet_period.get()),
+                        Duration::from_millis(new_config.max_wait.get()),
+                    );
+                    config = *new_config
+                }
+                Ok(DevThreadMessage::Update) => {
+                    let textures_dir = config.from_path.get().join("textures");
+                    if textures_dir.is_dir() {
+                        let textures = textures_dir
+                            .read_dir()
+                            .unwrap()
+                            .filter_map(|e| e.ok().map(|e| e.path()));
+                        parse_list.extend(textures);
+                    };
+                    parse_list.insert(config.from_path.get().join(PACK_FILE));
+                    debouncer.reset();
+                    force_build = true
                 }
-            } else {
-                //watch file
-                for event in file_watcher.get() {
+                Ok(DevThreadMessage::Converted) => {}
+                Ok(DevThreadMessage::Acknowledge) => {
+                    info!("Acknowledged {} DDS error(s)", dds_parser.errors().len());
+                    dds_parser.acknowledge();
+                }
+                Ok(DevThreadMessage::File(event)) => {
+                    //events sent before the watcher was closed
+                    if !config.hot_reload.get() {
+                        continue;
+                    }
                     match event {
                         FileUpdateMessage::ParseDDS(path) => {
+                            //converted dds files are not watched, rebuild after conversion
                             parse_list.insert(path);
+                            debouncer.event();
+                        }
+                        FileUpdateMessage::Rebuild => {
+                            if !debouncer.is_waiting() {
+                                info!("waiting for changes to settle...");
+                            }
+                            debouncer.event();
                         }
-                        FileUpdateMessage::Rebuild => need_build += 2,
                     };
                 }
+                Ok(DevThreadMessage::Close) | Err(RecvTimeoutError::Disconnected) => {
+                    file_watcher.close();
+                    return;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
             }
 
             //
+            let parse_dds = config.parse_dds.get();
+            if parse_dds == ParseDDS::Disabled {
+                parse_list.clear();
+            } else if !parse_list.is_empty() {
+                let referenced = load::referenced_textures(
+                    &config.from_path.get(),
+                    &config.name.get(),
+                    &config.texture_filter.get(),
+                );
+                let referenced = match referenced {
+                    Ok(referenced) => Some(referenced),
+                    Err(e) if parse_dds == ParseDDS::Referenced => {
+                        error!("Cannot read referenced textures: {}", e);
+                        Some(HashMap::new())
+                    }
+                    Err(e) => {
+                        debug!("No format hints: {}", e);
+                        None
+                    }
+                };
+                let formats = referenced
+                    .iter()
+                    .flatten()
+                    .filter_map(|(name, format)| Some((name.clone(), (*format)?)))
+                    .collect();
+                let only = referenced
+                    .filter(|_| parse_dds == ParseDDS::Referenced)
+                    .map(|referenced| referenced.into_keys().collect());
+                dds_parser.only(only);
+                dds_parser.formats(formats);
+            }
             for path in parse_list {
                 dds_parser.parse(path);
             }
+            dds_parser.poll();
 
-            //
-            let hot_rebuild = {
-              match need_build {
-                0 => false,
-                1 => {
-                  need_build -= 1; 
-                  println!("waiting... (waiting 3s)");
-                  thread::sleep(Duration::from_secs(3));
-                  true
-                },
-                _ => {
-                  need_build -= 1;
-                  println!("waiting... (waiting {}s)",3+need_build);
-                  thread::sleep(Duration::from_millis(500));
-                  false
+            let hot_rebuild = debouncer.ready();
+            if force_build || hot_rebuild {
+                debug!(
+                    "Build requested (forced: {}, hot: {})",
+                    force_build, hot_rebuild
+                );
+                build_pending = true;
+                if !dds_parser.idle() {
+                    info!("Build waits for texture conversion");
                 }
-              }
-            };
-            if dds_parser.finished() && (force_build || hot_rebuild) {
-                let path = config.from_path.get().clone();
-                if config.name.get().is_empty() {
-                    eprintln!("Miss Char name");
-                    continue;
-                } else if !path.join("assets").exists() {
-                    eprintln!("Assets folder not found");
-                    continue;
-                } else if !path.join("temp").exists() {
-                    eprintln!("Temp folder not found");
-                    continue;
+            }
+            if build_pending && dds_parser.idle() {
+                build_pending = false;
+                let started = Instant::now();
+                event::emit(Event::BuildStarted {
+                    name: config.name.get(),
+                    path: config.from_path.get(),
+                    dev_mode: true,
+                });
+                let mut targets = vec![];
+                let result = build(&config, &dds_parser, &mut targets);
+                if let Err(error) = &result {
+                    error!("Build failed: {}", error);
                 }
 
-                load::build_genshin_mod(&path, config.name.get(), true, String::new()).unwrap();
-
-                let from_path = path.join("output");
-                let to_path = config.to_path.get();
-
-                //if input != output
-                if path != to_path {
-                    move_files(from_path, to_path, 0);
-                    fn move_files(from: PathBuf, to: PathBuf, depth: usize) {
-                        for file in from.read_dir().unwrap() {
-                            if let Ok(file) = file {
-                                let filename = file.file_name().to_string_lossy().to_string();
-                                let filepath = file.path();
-                                if filepath.is_dir() {
-                                    let output_folder = to.join(filename);
-                                    println!(
-                                        "* {} -> {}",
-                                        filepath.display(),
-                                        output_folder.display()
-                                    );
-                                    if !output_folder.exists() {
-                                        fs::create_dir(&output_folder).unwrap();
-                                    }
-                                    move_files(filepath, output_folder, depth + 1);
-                                } else {
-                                    let to = to.join(filename);
-                                    println!(
-                                        "{}* {} -> {}",
-                                        "|".repeat(depth),
-                                        filepath.display(),
-                                        to.display()
-                                    );
-                                    fs::rename(&filepath, &to)
-                                        .unwrap_or_else(|e| eprintln!("{}", e))
-                                }
-                            }
-                        }
+                if config.reload_trigger.get() {
+                    for target in &targets {
+                        hook::write_reload_trigger(target).unwrap_or_else(|e| error!("{}", e));
                     }
                 }
-            }
 
-            //add idle time
-            thread::sleep(Duration::from_millis(500));
+                let path = config.from_path.get();
+                let result = BuildResult {
+                    name: config.name.get(),
+                    output_path: path.join("output"),
+                    mod_path: path,
+                    targets,
+                    error: result.err(),
+                    duration: started.elapsed(),
+                };
+                event::emit(Event::BuildFinished {
+                    name: result.name.clone(),
+                    success: result.success(),
+                    error: result.error.clone(),
+                    duration_ms: result.duration.as_millis() as u64,
+                    targets: result.targets.clone(),
+                });
+                let hooks = config.hooks.get();
+                if !hooks.is_empty() {
+                    hook::run_hooks(&hooks, &result);
+                }
+                *last_build.lock().unwrap() = Some((Instant::now(), result));
+            }
         }
     });
 
+    tx.send(DevThreadMessage::Config(Box::new(config.clone())))
+        .unwrap();
+
+    let session = DevSession {
+        project: Arc::new(Mutex::new(project)),
+        saved: Arc::new(Mutex::new(saved)),
+        project_path,
+        tx,
+        dds_status,
+        last_build,
+        closed: Arc::default(),
+    };
+    let control = config.control.get();
+    if !control.is_empty() {
+        control::serve(&control, session.clone())?;
+    }
+
+    let terminal = io::stdin().is_terminal() && io::stdout().is_terminal() && !event::to_stdout();
+    if config.tui.get() && terminal {
+        tui::run(&session)
+    } else {
+        menu(&session);
+        Ok(())
+    }
+}
+
+/// plain prompt menu, for scripts and terminals without the ui
+fn menu(session: &DevSession) {
     loop {
-        let action = list_and_choose(
-            "Settings",
-            vec![
-                config.name.format("Character Name"),
-                config.from_path.format("Mod Path (From)"),
-                config.to_path.format("Install Path (To)"),
-                config.parse_dds.format("Auto Parse to DDS"),
-                config.hot_reload.format("Hot Reload"),
-                "Exit".to_string(),
-                "Update".to_string(),
-            ],
-            false,
-        );
+        let mut list = session.settings();
+        let settings = list.len();
+        list.extend([
+            "Exit".to_string(),
+            "Update".to_string(),
+            "Acknowledge DDS Errors".to_string(),
+        ]);
+        let index = list_and_choose("Settings", list, false);
+        //a control client may have closed the session meanwhile
+        if session.closed() {
+            break;
+        }
+        match index {
+            index if index < settings => session.edit(index),
+            index if index == settings => {
+                session.close();
+                break;
+            }
+            index if index == settings + 1 => session.update(),
+            _ => session.acknowledge(),
+        }
+    }
+}
+
+/// finish time and result of the last dev build
+pub type LastBuild = Arc<Mutex<Option<(Instant, BuildResult)>>>;
+
+/// settings menu state, shared by the prompt menu, the terminal ui and the control server
+#[derive(Clone)]
+pub struct DevSession {
+    project: Arc<Mutex<DevProject>>,
+    /// `dev.hl.json` as on disk, without flag and env overrides
+    saved: Arc<Mutex<DevProject>>,
+    project_path: PathBuf,
+    tx: mpsc::Sender<DevThreadMessage>,
+    pub dds_status: Arc<Mutex<DDSStatus>>,
+    pub last_build: LastBuild,
+    closed: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl DevSession {
+    fn config(&self) -> DevConfig {
+        self.project.lock().unwrap().config()
+    }
+    pub fn profile(&self) -> String {
+        self.project.lock().unwrap().current.clone()
+    }
+    pub fn hot_reload(&self) -> bool {
+        self.config().hot_reload.get()
+    }
+    pub fn settings(&self) -> Vec<String> {
+        let config = self.config();
+        vec![
+            format!("Profile: {}", self.profile()),
+            config.name.format("Character Name"),
+            config.from_path.format("Mod Path (From)"),
+            config.targets.format("Install Targets (To)"),
+            config.parse_dds.format("Auto Parse to DDS"),
+            config.dds_policy.format("DDS Encoding"),
+            config.texture_filter.format("Texture Filter"),
+            config.placeholder_textures.format("Placeholder Textures"),
+            config.hot_reload.format("Hot Reload"),
+            config.poll_watch.format("Polling Watcher"),
+            config.quiet_period.format("Rebuild Quiet Period"),
+            config.max_wait.format("Rebuild Max Wait"),
+            config.ignore.format("Ignore Patterns"),
+            config.reload_trigger.format("Reload Trigger File"),
+            config.hooks.format("Post-build Hooks"),
+            config.tui.format("Terminal UI (next start)"),
+            config.control.format("Control Server (next start)"),
+        ]
+    }
+    /// change the setting at `index` of `settings()` with its prompt
+    pub fn edit(&self, index: usize) {
+        //prompts run unlocked, the control server may change settings meanwhile
+        let mut config = self.config();
         let mut force = false;
-        match action {
-            0 => config.name.set(),
-            1 => config.from_path.set(),
-            2 => config.to_path.set(),
-            3 => config.parse_dds.set(),
-            4 => {
-                config.hot_reload.set();
+        match index {
+            0 => {
+                let mut project = self.project.lock().unwrap().clone();
+                project.choose();
+                config = project.config();
+                *self.project.lock().unwrap() = project;
+                self.save_profiles();
                 force = true;
             }
-            5 => {
-                tx.send(DevThreadMessage::Close).unwrap();
-                break;
+            1 => config.name.set(),
+            2 => config.from_path.set(),
+            3 => config.targets.set(),
+            4 => config.parse_dds.set(),
+            5 => config.dds_policy.set(),
+            6 => config.texture_filter.set(),
+            7 => config.placeholder_textures.set(),
+            8 => {
+                config.hot_reload.set();
+                force = true;
             }
-            6 => force = true,
+            9 => config.poll_watch.set(),
+            10 => config.quiet_period.set(),
+            11 => config.max_wait.set(),
+            12 => config.ignore.set(),
+            13 => config.reload_trigger.set(),
+            14 => config.hooks.set(),
+            15 => config.tui.set(),
+            16 => config.control.set(),
             _ => unreachable!(),
         };
-
-        tx.send(DevThreadMessage::Config(config.clone())).unwrap();
+        self.apply(config, force);
+    }
+    /// set a setting by its `dev.hl.json` key, `profile` switches profile
+    pub fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        let key = setting_key(key);
+        if key == "profile" {
+            let config = {
+                let mut project = self.project.lock().unwrap();
+                let config = project.config();
+                project.profiles.entry(value.to_string()).or_insert(config);
+                project.current = value.to_string();
+                project.config()
+            };
+            self.save_profiles();
+            self.apply(config, true);
+        } else {
+            let config = self.config().set(&key, value)?;
+            self.apply(config, key == "hot_reload");
+        }
+        Ok(())
+    }
+    pub fn toggle_hot_reload(&self) {
+        self.edit(8);
+    }
+    /// rebuild now
+    pub fn update(&self) {
+        self.apply(self.config(), true);
+    }
+    pub fn acknowledge(&self) {
+        self.tx.send(DevThreadMessage::Acknowledge).ok();
+    }
+    pub fn close(&self) {
+        self.tx.send(DevThreadMessage::Close).ok();
+        let (closed, changed) = &*self.closed;
+        *closed.lock().unwrap() = true;
+        changed.notify_all();
+    }
+    pub fn closed(&self) -> bool {
+        *self.closed.0.lock().unwrap()
+    }
+    /// block until the menu, the terminal ui or a control client closes the session
+    pub fn wait_closed(&self) {
+        let (closed, changed) = &*self.closed;
+        let _closed = changed
+            .wait_while(closed.lock().unwrap(), |closed| !*closed)
+            .unwrap();
+    }
+    /// profile, settings, dds queue and last build as json
+    pub fn status(&self) -> serde_json::Value {
+        let last_build = self
+            .last_build
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(finished, result)| {
+                serde_json::json!({
+                    "name": result.name,
+                    "success": result.success(),
+                    "error": result.error,
+                    "duration_ms": result.duration.as_millis() as u64,
+                    "seconds_ago": finished.elapsed().as_secs(),
+                    "targets": result.targets,
+                })
+            });
+        serde_json::json!({
+            "profile": self.profile(),
+            "config": self.config(),
+            "dds": *self.dds_status.lock().unwrap(),
+            "last_build": last_build,
+        })
+    }
+    /// save the changed settings and send them to the worker
+    fn apply(&self, config: DevConfig, force: bool) {
+        let changed = {
+            let mut project = self.project.lock().unwrap();
+            let changed = project.config().changed(&config);
+            project.set_config(config.clone());
+            changed
+        };
+        if !changed.is_empty() {
+            self.save(&changed);
+        }
+        //the worker is gone once the session is closed
+        self.tx
+            .send(DevThreadMessage::Config(Box::new(config)))
+            .ok();
 
         if force {
-            tx.send(DevThreadMessage::Update).unwrap();
+            self.tx.send(DevThreadMessage::Update).ok();
         }
     }
-    Ok(())
+    /// write `changed` settings of the current profile,
+    /// overrides of other settings stay out of `dev.hl.json`
+    fn save(&self, changed: &[String]) {
+        let project = self.project.lock().unwrap();
+        let mut saved = self.saved.lock().unwrap();
+        //a profile only known from `--profile` starts from the settings on disk
+        let file_config = match saved.profiles.get(&project.current) {
+            Some(config) => config.clone(),
+            None => saved.config(),
+        };
+        let mut json = serde_json::to_value(file_config).unwrap();
+        let edited = serde_json::to_value(project.config()).unwrap();
+        for key in changed {
+            json[key] = edited[key].clone();
+        }
+        let config = serde_json::from_value(json).unwrap();
+        saved.profiles.insert(project.current.clone(), config);
+        saved
+            .save(&self.project_path)
+            .unwrap_or_else(|e| error!("Cannot save {}: {}", DEV_PROJECT, e));
+    }
+    /// write the profile list and the chosen profile
+    fn save_profiles(&self) {
+        let project = self.project.lock().unwrap();
+        let mut saved = self.saved.lock().unwrap();
+        saved.current = project.current.clone();
+        saved
+            .profiles
+            .retain(|name, _| project.profiles.contains_key(name));
+        for (name, config) in &project.profiles {
+            saved
+                .profiles
+                .entry(name.clone())
+                .or_insert_with(|| config.clone());
+        }
+        saved
+            .save(&self.project_path)
+            .unwrap_or_else(|e| error!("Cannot save {}: {}", DEV_PROJECT, e));
+    }
 }
 
 enum DevThreadMessage {
-    Config(DevConfig),
+    Config(Box<DevConfig>),
     Update,
+    Acknowledge,
+    /// progress of the dds conversion pool
+    Converted,
+    File(FileUpdateMessage),
     Close,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone)]
+/// trailing edge debounce, fires after `quiet` without events
+/// but no later than `max_wait` after the first event
+struct Debouncer {
+    quiet: Duration,
+    max_wait: Duration,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl Debouncer {
+    fn new(quiet: Duration, max_wait: Duration) -> Debouncer {
+        Debouncer {
+            quiet,
+            max_wait,
+            first: None,
+            last: None,
+        }
+    }
+    fn set(&mut self, quiet: Duration, max_wait: Duration) {
+        self.quiet = quiet;
+        self.max_wait = max_wait;
+    }
+    fn event(&mut self) {
+        let now = Instant::now();
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+    fn is_waiting(&self) -> bool {
+        self.first.is_some()
+    }
+    fn deadline(&self) -> Option<Instant> {
+        let (first, last) = (self.first?, self.last?);
+        Some((last + self.quiet).min(first + self.max_wait))
+    }
+    /// true once the deadline passed, then waits for the next event
+    fn ready(&mut self) -> bool {
+        match self.deadline() {
+            Some(deadline) if deadline <= Instant::now() => {
+                self.reset();
+                true
+            }
+            _ => false,
+        }
+    }
+    fn reset(&mut self) {
+        self.first = None;
+        self.last = None;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 enum FileUpdateMessage {
     ParseDDS(PathBuf),
     Rebuild,
 }
 
+/// file extensions which trigger a rebuild, by folder
+const REBUILD_EXTENSIONS: [(&str, &[&str]); 2] = [
+    ("assets", &["dds", "png", "json"]),
+    ("temp", &["vb", "ib", "fmt", "buf"]),
+];
+
+/// file extensions which are converted to dds
+const TEXTURE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "tga", "bmp", "tif", "tiff"];
+
 struct FileWatcher {
-    events: Arc<Mutex<HashSet<FileUpdateMessage>>>,
-    inner: ReadDirectoryChangesWatcher,
+    inner: Box<dyn Watcher + Send>,
     curr_ref: Arc<Mutex<PathBuf>>,
     current: Option<PathBuf>,
+    ignore: Arc<Mutex<Vec<Pattern>>>,
 }
 
 impl FileWatcher {
-    fn new() -> FileWatcher {
-        let events = Arc::new(Mutex::new(HashSet::new()));
-        let events_copied = events.clone();
+    /// `poll` scans the folder instead of native events,
+    /// for network shares and WSL mounts where events are unreliable,
+    /// it is also used when native events cannot start (e.g. out of inotify instances)
+    fn new(
+        poll: bool,
+        tx: mpsc::Sender<DevThreadMessage>,
+        write_log: WriteLog,
+    ) -> Result<FileWatcher, String> {
+        let tx = Arc::new(Mutex::new(tx));
         let curr_ref = Arc::new(Mutex::new(PathBuf::new()));
         let source = curr_ref.clone();
-        let watcher =
-            notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
-                if let Ok(e) = res {
-                    let source = source.lock().unwrap().clone();
-                    let root_path = PathBuf::from(source);
-
-                    let mut events = events_copied.lock().unwrap();
-                    for path in e.paths {
-                        let rebuild = path.starts_with(root_path.join("assets"))
-                            || path.starts_with(root_path.join("temp"));
-                        let update_texture = path.starts_with(root_path.join("textures"));
-                        if rebuild {
-                            events.insert(FileUpdateMessage::Rebuild);
-                        } else if update_texture {
-                            events.insert(FileUpdateMessage::ParseDDS(path));
-                        };
+        let ignore = Arc::new(Mutex::new(Vec::new()));
+        let ignore_copied = ignore.clone();
+        let handler = move |res: Result<notify::Event, notify::Error>| {
+            if let Ok(e) = res {
+                let root_path = source.lock().unwrap().clone();
+                let ignore = ignore_copied.lock().unwrap();
+
+                let mut events = HashSet::new();
+                for path in e.paths {
+                    if write_log.contains(&path) {
+                        trace!("Ignore own write {}", path.display());
+                        continue; //written by HornyLoader
+                    }
+                    let display = path.display().to_string();
+                    match route(&root_path, path, &ignore) {
+                        Some(event) => {
+                            trace!("{:?} from {}", event, display);
+                            events.insert(event);
+                        }
+                        None => trace!("Ignore {}", display),
                     }
                 }
-            })
-            .unwrap();
-        Self {
+
+                let tx = tx.lock().unwrap();
+                for event in events {
+                    tx.send(DevThreadMessage::File(event)).ok();
+                }
+            }
+        };
+        let poll_watcher = |handler| {
+            let config = notify::Config::default().with_poll_interval(Duration::from_secs(1));
+            notify::PollWatcher::new(handler, config)
+                .map_err(|e| format!("Cannot start the file watcher: {}", e))
+        };
+        let watcher: Box<dyn Watcher + Send> = if poll {
+            Box::new(poll_watcher(handler)?)
+        } else {
+            match notify::recommended_watcher(handler.clone()) {
+                Ok(watcher) => Box::new(watcher),
+                Err(e) => {
+                    warn!("Native file events unavailable ({}), polling instead", e);
+                    Box::new(poll_watcher(handler)?)
+                }
+            }
+        };
+        Ok(Self {
             inner: watcher,
             current: None,
             curr_ref,
-            events,
-        }
+            ignore,
+        })
+    }
+    fn set_ignore(&mut self, patterns: &[String]) {
+        let patterns = patterns
+            .iter()
+            .filter_map(|pattern| match Pattern::new(pattern) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    error!("Invalid ignore pattern `{}`: {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        *self.ignore.lock().unwrap() = patterns;
     }
     fn watch(&mut self, path: PathBuf) {
         if Some(path.clone()) == self.current {
             return; //skip
         }
         self.close();
-        self.inner
+        if let Err(e) = self
+            .inner
             .watch(path.as_path(), notify::RecursiveMode::Recursive)
-            .unwrap();
+        {
+            error!("Cannot watch {}: {}", path.display(), e);
+            return;
+        }
         self.current = Some(path.clone());
         *self.curr_ref.lock().unwrap() = path;
     }
     fn close(&mut self) {
         if let Some(path) = &self.current {
-            self.inner.unwatch(path.as_path()).unwrap();
+            //fails if the folder was removed meanwhile
+            if let Err(e) = self.inner.unwatch(path.as_path()) {
+                debug!("Cannot unwatch {}: {}", path.display(), e);
+            }
         }
         self.current = None;
-        self.get();
     }
-    fn get(&self) -> HashSet<FileUpdateMessage> {
-        let mut events = self.events.lock().unwrap();
-        mem::replace(events.borrow_mut(), HashSet::new())
+}
+
+/// build the mod and sync it, `synced` gets the targets written even if another target failed
+fn build(
+    config: &DevConfig,
+    dds_parser: &DDSParser,
+    synced: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    if !dds_parser.errors().is_empty() {
+        let mut error =
+            "DDS conversion failed, fix the textures or acknowledge the errors".to_string();
+        for (path, message) in dds_parser.errors() {
+            error += &format!("\n- {}: {}", path.display(), message);
+        }
+        return Err(error);
+    }
+
+    let path = config.from_path.get().clone();
+    if config.name.get().is_empty() {
+        return Err("Miss Char name".to_string());
+    } else if !path.join("assets").exists() {
+        return Err("Assets folder not found".to_string());
+    } else if !path.join("temp").exists() {
+        return Err("Temp folder not found".to_string());
     }
+
+    load::build_genshin_mod(
+        &path,
+        config.name.get(),
+        &config.texture_filter.get(),
+        config.placeholder_textures.get(),
+        String::new(),
+    )?;
+
+    let from_path = path.join("output");
+    let mut failed = Vec::new();
+    for target in config.targets.get().iter().filter(|t| t.enabled) {
+        //each mod gets its own folder, other mods in the target keep their files
+        let mod_folder = target.path.join(config.name.get());
+        //if input != output
+        if target.path == path || mod_folder == path {
+            continue;
+        }
+        match sync::sync_folder(&from_path, &mod_folder) {
+            Ok(report) => {
+                info!("Synced to {}: {}", mod_folder.display(), report);
+                synced.push(mod_folder);
+            }
+            Err(error) => failed.push((mod_folder, error)),
+        }
+    }
+    if !failed.is_empty() {
+        let mut error = format!("Sync failed on {} target(s)", failed.len());
+        for (target, message) in failed {
+            error += &format!("\n- {}: {}", target.display(), message);
+        }
+        return Err(error);
+    }
+    Ok(())
 }
 
-struct DDSParser {
-    path: PathBuf,
-    running: Arc<Mutex<HashSet<PathBuf>>>,
+/// decide what a changed file triggers, by folder and extension
+fn route(root_path: &Path, path: PathBuf, ignore: &[Pattern]) -> Option<FileUpdateMessage> {
+    let relative = path.strip_prefix(root_path).ok()?;
+    if ignore.iter().any(|pattern| pattern.matches_path(relative)) {
+        return None;
+    }
+
+    if relative == Path::new(PACK_FILE) {
+        return Some(FileUpdateMessage::ParseDDS(path));
+    }
+
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if relative.starts_with("textures") {
+        return TEXTURE_EXTENSIONS
+            .contains(&extension.as_str())
+            .then_some(FileUpdateMessage::ParseDDS(path));
+    }
+
+    let rebuild = REBUILD_EXTENSIONS.iter().any(|(folder, extensions)| {
+        relative.starts_with(folder) && extensions.contains(&extension.as_str())
+    });
+    rebuild.then_some(FileUpdateMessage::Rebuild)
 }
 
-impl DDSParser {
-    fn new(path: PathBuf) -> DDSParser {
-        let running = Arc::new(Mutex::new(HashSet::new()));
-        DDSParser { running, path }
+/// `dev.hl.json` key of a flag, env var or control setting name,
+/// `to_path` is the old name of `targets`
+pub fn setting_key(key: &str) -> String {
+    match key.to_lowercase().replace('-', "_").as_str() {
+        "to_path" => "targets".to_string(),
+        key => key.to_string(),
     }
-    fn parse(&mut self, path: PathBuf) {
-        if !path.is_file() {
-            return;
-        }
+}
 
-        let in_queue = self.running.lock().unwrap().insert(path.clone());
-        if !in_queue {
-            return;
-        }
+/// settings from `HL_DEV_<SETTING>` env vars and `--<setting> <value>` flags,
+/// flags are applied last so they win
+fn overrides() -> Result<Vec<(String, String)>, String> {
+    let mut overrides: Vec<(String, String)> = env::vars()
+        .filter_map(|(key, value)| Some((setting_key(key.strip_prefix("HL_DEV_")?), value)))
+        .collect();
 
-        let running = self.running.clone();
-        let output_path = self.path.clone();
-        thread::spawn(move || {
-            if let Ok(image) = image::open(path.clone()) {
-                println!("Parse dds file ({})", path.display());
-                let image = image.to_rgba8();
-                let dds = image_dds::dds_from_image(
-                    &image,
-                    image_dds::ImageFormat::BC7Srgb,
-                    image_dds::Quality::Slow,
-                    image_dds::Mipmaps::Disabled,
-                )
-                .unwrap();
-                let dds_path = path.file_stem().unwrap().to_string_lossy().to_string() + ".dds";
-                let mut writer = std::io::BufWriter::new(
-                    std::fs::File::create(&output_path.join("assets").join(dds_path)).unwrap(),
-                );
-                dds.write(&mut writer).unwrap();
-            }
-            running.lock().unwrap().remove(&path);
-        });
+    let mut args = crate::args()
+        .into_iter()
+        .skip_while(|arg| !arg.starts_with("--"));
+    while let Some(arg) = args.next() {
+        let Some(key) = arg.strip_prefix("--") else {
+            return Err(format!("Unexpected argument `{}`", arg));
+        };
+        let (key, value) = match key.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => match args.next() {
+                Some(value) => (key.to_string(), value),
+                None => return Err(format!("Missing value for `--{}`", key)),
+            },
+        };
+        overrides.push((setting_key(&key), value));
     }
-    /// block util finished
-    fn finished(&self) -> bool {
-        if self.running.lock().unwrap().len() == 0 {return true;}
-        while self.running.lock().unwrap().len() != 0 {
-            thread::sleep(Duration::from_secs(1));
+    Ok(overrides)
+}
+
+/// dev settings of a workspace, saved in `dev.hl.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DevProject {
+    current: String,
+    profiles: BTreeMap<String, DevConfig>,
+}
+
+impl DevProject {
+    fn load(path: &Path) -> Result<DevProject, String> {
+        if !path.exists() {
+            return Ok(DevProject {
+                current: "Default".to_string(),
+                profiles: BTreeMap::new(),
+            });
         }
-        false
+        info!("Loading `{}`", DEV_PROJECT);
+        let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&json).map_err(|e| format!("Invalid {}: {}", DEV_PROJECT, e))
+    }
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+    fn config(&self) -> DevConfig {
+        self.profiles
+            .get(&self.current)
+            .cloned()
+            .unwrap_or_else(DevConfig::new)
+    }
+    fn set_config(&mut self, config: DevConfig) {
+        self.profiles.insert(self.current.clone(), config);
     }
-    fn reload(&mut self, path: PathBuf) {
-        if path != self.path {
-            self.path = path;
+    /// switch to another profile, a new profile copies the current settings
+    fn choose(&mut self) {
+        let mut list: Vec<String> = self.profiles.keys().cloned().collect();
+        list.push("New Profile".to_string());
+        //nothing to delete before the first save
+        if !self.profiles.is_empty() {
+            list.push("Delete Profile".to_string());
+        }
+        let index = list_and_choose("Profiles", list.clone(), false);
+
+        if index < self.profiles.len() {
+            self.current = list[index].clone();
+        } else if index == self.profiles.len() {
+            prompt!("waiting input (profile name)");
+            let name = read_input();
+            if name.is_empty() {
+                prompt!("No Profile name");
+                return;
+            }
+            let config = self.config();
+            self.profiles.entry(name.clone()).or_insert(config);
+            self.current = name;
+        } else {
+            let names: Vec<String> = self.profiles.keys().cloned().collect();
+            let index = list_and_choose("Delete Profile", names.clone(), false);
+            self.profiles.remove(&names[index]);
+            if names[index] == self.current {
+                self.current = self
+                    .profiles
+                    .keys()
+                    .next()
+                    .cloned()
+                    .unwrap_or("Default".to_string());
+            }
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 struct DevConfig {
     name: StringOption,
-    to_path: FolderOption,
+    #[serde(alias = "to_path")]
+    targets: TargetsOption,
     from_path: FolderOption,
-    parse_dds: BoolOption,
+    parse_dds: ParseDDSOption,
+    dds_policy: DDSPolicyOption,
+    texture_filter: TextureFilterOption,
+    placeholder_textures: BoolOption,
     hot_reload: BoolOption,
+    poll_watch: BoolOption,
+    quiet_period: MillisOption,
+    max_wait: MillisOption,
+    ignore: ListOption,
+    reload_trigger: BoolOption,
+    hooks: HooksOption,
+    tui: BoolOption,
+    /// control server address, empty to disable
+    control: StringOption,
+}
+
+impl DevConfig {
+    /// keys of the settings which differ in `other`
+    fn changed(&self, other: &DevConfig) -> Vec<String> {
+        let before = serde_json::to_value(self).unwrap();
+        let after = serde_json::to_value(other).unwrap();
+        after
+            .as_object()
+            .unwrap()
+            .iter()
+            .filter(|(key, value)| before.get(key.as_str()) != Some(*value))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+    /// folders typed in the menu must exist, the same goes for flags and the control server
+    fn check_folders(&self, key: &str) -> Result<(), String> {
+        let folders = match key {
+            "from_path" => vec![self.from_path.get()],
+            "targets" => self.targets.get().into_iter().map(|t| t.path).collect(),
+            _ => vec![],
+        };
+        match folders.iter().find(|folder| !folder.is_dir()) {
+            Some(folder) => Err(format!("`{}` is not a folder", folder.display())),
+            None => Ok(()),
+        }
+    }
+    /// set a setting by its `dev.hl.json` key, the value is json or plain text
+    fn set(&self, key: &str, value: &str) -> Result<DevConfig, String> {
+        let key = setting_key(key);
+        let key = key.as_str();
+        let mut json = serde_json::to_value(self).unwrap();
+        let current = json
+            .get(key)
+            .cloned()
+            .ok_or(format!("Unknown setting `{}`", key))?;
+
+        //plain text lists are split by `,`
+        let text = match current {
+            serde_json::Value::Array(_) => serde_json::Value::Array(
+                value
+                    .split(',')
+                    .map(|item| item.trim())
+                    .filter(|item| !item.is_empty())
+                    .map(|item| serde_json::Value::String(item.to_string()))
+                    .collect(),
+            ),
+            _ => serde_json::Value::String(value.to_string()),
+        };
+        let mut candidates = vec![text];
+        if let Ok(parsed) = serde_json::from_str(value) {
+            candidates.insert(0, parsed);
+        }
+
+        let mut error = String::new();
+        for candidate in candidates {
+            json[key] = candidate;
+            match serde_json::from_value::<DevConfig>(json.clone()) {
+                Ok(config) => {
+                    config.check_folders(key)?;
+                    info!("Set `{}` to {}", key, value);
+                    return Ok(config);
+                }
+                Err(e) => error = e.to_string(),
+            }
+        }
+        Err(format!("Invalid value for `{}`: {}", key, error))
+    }
+}
+
+impl Default for DevConfig {
+    fn default() -> Self {
+        DevConfig::new()
+    }
 }
 
 impl DevConfig {
@@ -338,15 +1001,31 @@ impl DevConfig {
         let current_path = env::current_dir().unwrap();
         DevConfig {
             name: StringOption::new(String::new()),
-            to_path: FolderOption::new(current_path.clone()),
+            targets: TargetsOption::new(Vec::new()),
             from_path: FolderOption::new(current_path.clone()),
-            parse_dds: BoolOption::new(true),
+            parse_dds: ParseDDSOption::new(ParseDDS::All),
+            dds_policy: DDSPolicyOption::new(DDSPolicy::default()),
+            texture_filter: TextureFilterOption::new(TextureFilter::default()),
+            placeholder_textures: BoolOption::new(false),
             hot_reload: BoolOption::new(false),
+            poll_watch: BoolOption::new(false),
+            quiet_period: MillisOption::new(1500),
+            max_wait: MillisOption::new(10000),
+            ignore: ListOption::new(
+                ["*.blend1", "*.swp", "*~", "*.tmp", "**/.#*"]
+                    .iter()
+                    .map(|pattern| pattern.to_string())
+                    .collect(),
+            ),
+            reload_trigger: BoolOption::new(false),
+            hooks: HooksOption::new(Vec::new()),
+            tui: BoolOption::new(true),
+            control: StringOption::new(String::new()),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct FolderOption(PathBuf);
 impl FolderOption {
     fn new(path: PathBuf) -> Self {
@@ -355,10 +1034,8 @@ impl FolderOption {
 }
 impl InputOption<PathBuf> for FolderOption {
     fn set(&mut self) {
-        let folder = FileDialog::new().pick_folder();
-        match folder {
-            Some(folder) => self.0 = folder,
-            None => println!("No Folder picked"),
+        if let Some(folder) = read_folder("Pick a folder") {
+            self.0 = folder;
         }
     }
 
@@ -371,7 +1048,104 @@ impl InputOption<PathBuf> for FolderOption {
     }
 }
 
-#[derive(Debug, Clone)]
+/// a 3dmigoto mods folder the build is synced to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InstallTarget {
+    path: PathBuf,
+    enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TargetsDef")]
+struct TargetsOption(Vec<InstallTarget>);
+impl TargetsOption {
+    fn new(targets: Vec<InstallTarget>) -> Self {
+        TargetsOption(targets)
+    }
+}
+impl InputOption<Vec<InstallTarget>> for TargetsOption {
+    fn set(&mut self) {
+        let mut list = self
+            .0
+            .iter()
+            .map(|target| {
+                let enabled = if target.enabled { "x" } else { " " };
+                format!("[{}] {}", enabled, target.path.display())
+            })
+            .collect::<Vec<_>>();
+        list.push("Add Target".to_string());
+        let index = list_and_choose("Install Targets", list, false);
+
+        if index == self.0.len() {
+            if let Some(path) =
+                read_folder("Pick a 3DMigoto Mods folder, the build goes into its <name> folder")
+            {
+                self.0.push(InstallTarget {
+                    path,
+                    enabled: true,
+                });
+            }
+            return;
+        }
+
+        let actions = vec!["Enable/Disable", "Change Folder", "Remove"];
+        match list_and_choose("Target", actions, false) {
+            0 => self.0[index].enabled = !self.0[index].enabled,
+            1 => {
+                if let Some(path) = read_folder(
+                    "Pick a 3DMigoto Mods folder, the build goes into its <name> folder",
+                ) {
+                    self.0[index].path = path;
+                }
+            }
+            _ => {
+                self.0.remove(index);
+            }
+        }
+    }
+    fn get(&self) -> Vec<InstallTarget> {
+        self.0.clone()
+    }
+
+    fn display(&self) -> String {
+        let enabled = self.0.iter().filter(|target| target.enabled).count();
+        match self.0.len() {
+            0 => "<NONE>".to_string(),
+            1 if enabled == 1 => self.0[0].path.to_string_lossy().to_string(),
+            len => format!("{}/{} enabled", enabled, len),
+        }
+    }
+}
+
+/// also reads the old single `to_path` folder
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TargetsDef {
+    List(Vec<InstallTarget>),
+    Paths(Vec<PathBuf>),
+    Single(PathBuf),
+}
+
+impl From<TargetsDef> for TargetsOption {
+    fn from(def: TargetsDef) -> Self {
+        TargetsOption(match def {
+            TargetsDef::List(targets) => targets,
+            TargetsDef::Paths(paths) => paths
+                .into_iter()
+                .map(|path| InstallTarget {
+                    path,
+                    enabled: true,
+                })
+                .collect(),
+            TargetsDef::Single(path) => vec![InstallTarget {
+                path,
+                enabled: true,
+            }],
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct StringOption(String);
 impl StringOption {
     fn new(string: String) -> Self {
@@ -380,7 +1154,7 @@ impl StringOption {
 }
 impl InputOption<String> for StringOption {
     fn set(&mut self) {
-        println!("waiting input");
+        prompt!("waiting input");
         self.0 = read_input()
     }
     fn get(&self) -> String {
@@ -396,7 +1170,98 @@ impl InputOption<String> for StringOption {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ListOption(Vec<String>);
+impl ListOption {
+    fn new(list: Vec<String>) -> Self {
+        ListOption(list)
+    }
+}
+impl InputOption<Vec<String>> for ListOption {
+    fn set(&mut self) {
+        prompt!("waiting input (split by `,`)");
+        self.0 = read_input()
+            .split(',')
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect();
+    }
+    fn get(&self) -> Vec<String> {
+        self.0.clone()
+    }
+
+    fn display(&self) -> String {
+        if self.0.is_empty() {
+            "<NONE>".to_string()
+        } else {
+            self.0.join(", ")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HooksOption(Vec<String>);
+impl HooksOption {
+    fn new(commands: Vec<String>) -> Self {
+        HooksOption(commands)
+    }
+}
+impl InputOption<Vec<String>> for HooksOption {
+    fn set(&mut self) {
+        let mut list = self.0.clone();
+        list.push("Add Hook".to_string());
+        let index = list_and_choose("Post-build Hooks (choose one to remove)", list, false);
+
+        if index < self.0.len() {
+            self.0.remove(index);
+            return;
+        }
+        prompt!("waiting input (shell command, build result in `HL_*` env vars)");
+        let command = read_input();
+        if command.is_empty() {
+            prompt!("No Command input");
+        } else {
+            self.0.push(command);
+        }
+    }
+    fn get(&self) -> Vec<String> {
+        self.0.clone()
+    }
+
+    fn display(&self) -> String {
+        match self.0.len() {
+            0 => "<NONE>".to_string(),
+            1 => self.0[0].clone(),
+            len => format!("{} commands", len),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MillisOption(u64);
+impl MillisOption {
+    fn new(millis: u64) -> Self {
+        MillisOption(millis)
+    }
+}
+impl InputOption<u64> for MillisOption {
+    fn set(&mut self) {
+        prompt!("waiting input (milliseconds)");
+        match read_input().parse() {
+            Ok(millis) => self.0 = millis,
+            Err(_) => prompt!("Not a number"),
+        }
+    }
+    fn get(&self) -> u64 {
+        self.0
+    }
+
+    fn display(&self) -> String {
+        format!("{}ms", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct BoolOption(bool);
 impl BoolOption {
     fn new(bool: bool) -> Self {
@@ -416,6 +1281,162 @@ impl InputOption<bool> for BoolOption {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum ParseDDS {
+    Disabled,
+    All,
+    /// only textures used by hash.json
+    Referenced,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ParseDDSDef")]
+struct ParseDDSOption(ParseDDS);
+impl ParseDDSOption {
+    fn new(mode: ParseDDS) -> Self {
+        ParseDDSOption(mode)
+    }
+}
+impl InputOption<ParseDDS> for ParseDDSOption {
+    fn set(&mut self) {
+        self.0 = match self.0 {
+            ParseDDS::Disabled => ParseDDS::All,
+            ParseDDS::All => ParseDDS::Referenced,
+            ParseDDS::Referenced => ParseDDS::Disabled,
+        }
+    }
+    fn get(&self) -> ParseDDS {
+        self.0
+    }
+
+    fn display(&self) -> String {
+        match self.0 {
+            ParseDDS::Disabled => "Disabled",
+            ParseDDS::All => "All",
+            ParseDDS::Referenced => "Referenced by hash.json",
+        }
+        .to_string()
+    }
+}
+
+/// also reads the old `true`/`false` setting
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ParseDDSDef {
+    Bool(bool),
+    Mode(ParseDDS),
+}
+
+impl From<ParseDDSDef> for ParseDDSOption {
+    fn from(def: ParseDDSDef) -> Self {
+        ParseDDSOption(match def {
+            ParseDDSDef::Bool(true) => ParseDDS::All,
+            ParseDDSDef::Bool(false) => ParseDDS::Disabled,
+            ParseDDSDef::Mode(mode) => mode,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TextureFilterOption(TextureFilter);
+impl TextureFilterOption {
+    fn new(filter: TextureFilter) -> Self {
+        TextureFilterOption(filter)
+    }
+}
+impl InputOption<TextureFilter> for TextureFilterOption {
+    fn set(&mut self) {
+        let mode = list_and_choose("Texture Filter Mode", vec!["Deny", "Allow"], true);
+        prompt!("waiting input (layout names, split by `,`)");
+        let list = read_input()
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        self.0 = match mode {
+            0 => TextureFilter::Deny(list),
+            1 => TextureFilter::Allow(list),
+            _ => unreachable!(),
+        }
+    }
+    fn get(&self) -> TextureFilter {
+        self.0.clone()
+    }
+
+    fn display(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DDSPolicyOption(DDSPolicy);
+impl DDSPolicyOption {
+    fn new(policy: DDSPolicy) -> Self {
+        DDSPolicyOption(policy)
+    }
+}
+impl InputOption<DDSPolicy> for DDSPolicyOption {
+    fn set(&mut self) {
+        let mut list = vec![format!("Default: {}", self.0.default)];
+        list.extend(
+            self.0
+                .layouts
+                .iter()
+                .map(|(layout, encoding)| format!("{}: {}", layout, encoding)),
+        );
+        list.push("Add Layout".to_string());
+        let index = list_and_choose("DDS Encoding", list, false);
+
+        let layout = match index {
+            0 => None,
+            i if i <= self.0.layouts.len() => self.0.layouts.keys().nth(i - 1).cloned(),
+            _ => {
+                prompt!("waiting input (layout name, e.g. `LightMap`)");
+                let layout = read_input();
+                if layout.is_empty() {
+                    prompt!("No Layout input");
+                    return;
+                }
+                Some(layout)
+            }
+        };
+
+        let format = list_and_choose("Format", ENCODE_FORMATS.to_vec(), false);
+        let quality = list_and_choose("Quality", vec!["Fast", "Normal", "Slow"], false);
+        let mipmaps = list_and_choose("Mipmaps", vec!["Disabled", "Enabled"], false);
+        prompt!("waiting input (max size, empty for no limit)");
+        let max_size = read_input().parse::<u32>().ok().filter(|size| *size > 0);
+        let power_of_two = list_and_choose("Power of Two", vec!["Disabled", "Enabled"], false);
+        let filter = if max_size.is_some() || power_of_two == 1 {
+            list_and_choose("Resize Filter", ResizeFilter::ALL.to_vec(), false)
+        } else {
+            ResizeFilter::ALL.len() - 1
+        };
+        let encoding = DDSEncoding {
+            format: ENCODE_FORMATS[format],
+            quality: [Quality::Fast, Quality::Normal, Quality::Slow][quality],
+            mipmaps: mipmaps == 1,
+            max_size,
+            filter: ResizeFilter::ALL[filter],
+            power_of_two: power_of_two == 1,
+        };
+
+        match layout {
+            Some(layout) => {
+                self.0.layouts.insert(layout, encoding);
+            }
+            None => self.0.default = encoding,
+        }
+    }
+    fn get(&self) -> DDSPolicy {
+        self.0.clone()
+    }
+
+    fn display(&self) -> String {
+        self.0.to_string()
+    }
+}
+
 trait InputOption<T> {
     fn get(&self) -> T;
     fn set(&mut self);
@@ -424,3 +1445,95 @@ trait InputOption<T> {
         format!("{}: {}", name, self.display())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn debounce_waits_for_quiet_period() {
+        let mut debouncer = Debouncer::new(millis(100), millis(1000));
+        assert_eq!(debouncer.deadline(), None);
+
+        let start = Instant::now();
+        debouncer.first = Some(start);
+        debouncer.last = Some(start + millis(300));
+        assert_eq!(debouncer.deadline(), Some(start + millis(400)));
+    }
+
+    #[test]
+    fn debounce_never_waits_past_max_wait() {
+        let mut debouncer = Debouncer::new(millis(100), millis(1000));
+        let start = Instant::now();
+        debouncer.first = Some(start);
+        debouncer.last = Some(start + millis(950));
+        assert_eq!(debouncer.deadline(), Some(start + millis(1000)));
+
+        //a max wait below the quiet period wins from the first event
+        debouncer.set(millis(1000), millis(200));
+        debouncer.last = Some(start);
+        assert_eq!(debouncer.deadline(), Some(start + millis(200)));
+    }
+
+    #[test]
+    fn debounce_fires_once_per_batch() {
+        let mut debouncer = Debouncer::new(Duration::ZERO, millis(1000));
+        assert!(!debouncer.ready());
+        debouncer.event();
+        assert!(debouncer.is_waiting());
+        assert!(debouncer.ready());
+        assert!(!debouncer.is_waiting());
+        assert!(!debouncer.ready());
+    }
+
+    fn route_in_mod(relative: &str) -> Option<FileUpdateMessage> {
+        let root = Path::new("/mods/Char");
+        let ignore = ["*.blend1", "**/.#*"].map(|pattern| Pattern::new(pattern).unwrap());
+        route(root, root.join(relative), &ignore)
+    }
+
+    #[test]
+    fn route_by_folder_and_extension() {
+        let root = Path::new("/mods/Char");
+        assert_eq!(
+            route_in_mod("textures/Body/Diffuse.PNG"),
+            Some(FileUpdateMessage::ParseDDS(
+                root.join("textures/Body/Diffuse.PNG")
+            ))
+        );
+        assert_eq!(
+            route_in_mod(PACK_FILE),
+            Some(FileUpdateMessage::ParseDDS(root.join(PACK_FILE)))
+        );
+        assert_eq!(route_in_mod("textures/notes.txt"), None);
+        assert_eq!(
+            route_in_mod("assets/hash.json"),
+            Some(FileUpdateMessage::Rebuild)
+        );
+        assert_eq!(
+            route_in_mod("temp/CharBody.vb"),
+            Some(FileUpdateMessage::Rebuild)
+        );
+        assert_eq!(route_in_mod("temp/CharBody.png"), None);
+        assert_eq!(route_in_mod("output/CharBody.ib"), None);
+        assert_eq!(route_in_mod("assets/Diffuse.dds.hl_tmp"), None);
+    }
+
+    #[test]
+    fn route_skips_ignored_and_outside_paths() {
+        assert_eq!(route_in_mod("Char.blend1"), None);
+        assert_eq!(route_in_mod("assets/.#hash.json"), None);
+        assert_eq!(
+            route(
+                Path::new("/mods/Char"),
+                PathBuf::from("/mods/Other/assets/hash.json"),
+                &[]
+            ),
+            None
+        );
+    }
+}
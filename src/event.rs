@@ -0,0 +1,125 @@
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// bumped on breaking changes of the event schema, see `EVENTS.md`
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// one line of the `--events` stream
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    BuildStarted {
+        name: String,
+        path: PathBuf,
+        dev_mode: bool,
+    },
+    ComponentCollected {
+        name: String,
+        component: String,
+        objects: usize,
+    },
+    TextureConverted {
+        path: PathBuf,
+        status: TextureStatus,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    FileWritten {
+        path: PathBuf,
+    },
+    Warning {
+        target: String,
+        message: String,
+    },
+    Error {
+        target: String,
+        message: String,
+    },
+    BuildFinished {
+        name: String,
+        success: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        duration_ms: u64,
+        targets: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureStatus {
+    Converted,
+    Skipped,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    v: u32,
+    /// unix time in milliseconds
+    time: u128,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+static SINK: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+static STDOUT: AtomicBool = AtomicBool::new(false);
+static SUBSCRIBERS: Mutex<Vec<mpsc::Sender<String>>> = Mutex::new(Vec::new());
+
+/// write events to `path`, `-` is stdout
+pub fn open(path: &str) -> Result<(), String> {
+    let sink: Box<dyn Write + Send> = if path == "-" {
+        STDOUT.store(true, Ordering::Relaxed);
+        Box::new(io::stdout())
+    } else {
+        Box::new(File::create(path).map_err(|e| format!("Cannot create {}: {}", path, e))?)
+    };
+    *SINK.lock().unwrap() = Some(sink);
+    Ok(())
+}
+
+/// stdout is reserved for events, everything else goes to stderr
+pub fn to_stdout() -> bool {
+    STDOUT.load(Ordering::Relaxed)
+}
+
+/// receive every following event as a json line, until the receiver is dropped
+pub fn subscribe() -> mpsc::Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap().push(tx);
+    rx
+}
+
+/// send `event` to the `--events` sink and subscribers, if any
+pub fn emit(event: Event) {
+    let mut sink = SINK.lock().unwrap();
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    if sink.is_none() && subscribers.is_empty() {
+        return;
+    }
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let line = Line {
+        v: SCHEMA_VERSION,
+        time,
+        event: &event,
+    };
+    let json = serde_json::to_string(&line).unwrap();
+    subscribers.retain(|subscriber| subscriber.send(json.clone()).is_ok());
+    if let Some(sink) = &mut *sink {
+        let _ = writeln!(sink, "{}", json);
+        let _ = sink.flush();
+    }
+}
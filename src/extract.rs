@@ -0,0 +1,104 @@
+use horny_loader::{dds, BREAK_LINE};
+use log::{debug, error, info};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{list_and_choose, read_input};
+
+/// decode the dds textures of a mod back to png,
+/// into the `textures` folder used by dev mode
+pub fn main() -> Result<(), String> {
+    let current_path = env::current_dir().unwrap();
+    prompt!("Mod or assets folder (empty for current folder)");
+    let input = read_input();
+    let source = if input.is_empty() {
+        current_path.clone()
+    } else {
+        PathBuf::from(input)
+    };
+    if !source.is_dir() {
+        return Err(format!("`{}` is not a folder", source.display()));
+    }
+
+    // hash.json described assets keep their workspace, other mods extract into current folder
+    let (search, textures) = if source.join("assets").join("hash.json").exists() {
+        (source.join("assets"), source.join("textures"))
+    } else if source.join("hash.json").exists() {
+        let workspace = source.parent().unwrap_or(&current_path).to_path_buf();
+        (source.clone(), workspace.join("textures"))
+    } else {
+        (source.clone(), current_path.join("textures"))
+    };
+
+    let mut files = vec![];
+    find_dds(&search, &mut files);
+    if files.is_empty() {
+        return Err(format!("No dds file found in `{}`", search.display()));
+    }
+
+    info!("{}", BREAK_LINE);
+    info!("Source Folder: {}", search.display());
+    info!("Textures Folder: {}", textures.display());
+    info!("Textures: {}", files.len());
+    info!("{}", BREAK_LINE);
+
+    let exists = files
+        .iter()
+        .filter(|file| textures.join(png_name(file)).exists())
+        .count();
+    let overwrite = exists != 0
+        && list_and_choose(
+            format!("{} texture(s) already exist", exists),
+            vec!["Skip", "Overwrite"],
+            true,
+        ) == 1;
+
+    fs::create_dir_all(&textures).map_err(|e| e.to_string())?;
+
+    let mut failed = 0;
+    for file in &files {
+        let png_path = textures.join(png_name(file));
+        if png_path.exists() && !overwrite {
+            debug!("Skip {}, already exists", png_path.display());
+            continue;
+        }
+        match dds::png_from_dds(file, &png_path) {
+            Ok(format) => info!("{} ({}) -> {}", file.display(), format, png_path.display()),
+            Err(error) => {
+                failed += 1;
+                error!("Failed {}: {}", file.display(), error)
+            }
+        }
+    }
+
+    info!("{}", BREAK_LINE);
+    if failed == 0 {
+        info!("Extract finished");
+        Ok(())
+    } else {
+        Err(format!("{} texture(s) could not be extracted", failed))
+    }
+}
+
+fn find_dds(path: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = path.read_dir() else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_dds(&path, files);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("dds"))
+        {
+            files.push(path);
+        }
+    }
+}
+
+fn png_name(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().to_string() + ".png"
+}
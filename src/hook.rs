@@ -0,0 +1,102 @@
+use log::{debug, error, info, warn};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// written into each install target after a build, for a companion ini to watch
+pub const RELOAD_TRIGGER: &str = "hl_reload.txt";
+
+/// outcome of a dev build, passed to hooks as `HL_*` environment variables
+#[derive(Debug, Clone)]
+pub struct BuildResult {
+    pub name: String,
+    pub mod_path: PathBuf,
+    pub output_path: PathBuf,
+    /// mod folders synced by this build, `<target>/<name>`
+    pub targets: Vec<PathBuf>,
+    pub error: Option<String>,
+    pub duration: Duration,
+}
+
+impl BuildResult {
+    pub fn success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn envs(&self) -> Vec<(&'static str, String)> {
+        let targets = env::join_paths(&self.targets)
+            .map(|paths| paths.to_string_lossy().to_string())
+            .unwrap_or_default();
+        vec![
+            (
+                "HL_SUCCESS",
+                if self.success() { "1" } else { "0" }.to_string(),
+            ),
+            ("HL_NAME", self.name.clone()),
+            ("HL_MOD_PATH", self.mod_path.to_string_lossy().to_string()),
+            (
+                "HL_OUTPUT_PATH",
+                self.output_path.to_string_lossy().to_string(),
+            ),
+            ("HL_TARGETS", targets),
+            ("HL_ERROR", self.error.clone().unwrap_or_default()),
+            ("HL_DURATION_MS", self.duration.as_millis().to_string()),
+        ]
+    }
+}
+
+/// run every hook command through the shell, in the mod folder,
+/// their output goes to the log so it never mixes into the event stream or the terminal ui
+pub fn run_hooks(commands: &[String], result: &BuildResult) {
+    for command in commands {
+        info!("Hook: {}", command);
+        let mut process = if cfg!(windows) {
+            let mut process = Command::new("cmd");
+            process.arg("/C").arg(command);
+            process
+        } else {
+            let mut process = Command::new("sh");
+            process.arg("-c").arg(command);
+            process
+        };
+        if result.mod_path.is_dir() {
+            process.current_dir(&result.mod_path);
+        }
+
+        debug!("Hook env: {:?}", result.envs());
+        let output = process
+            .envs(result.envs())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+        match output {
+            Ok(output) => {
+                for line in String::from_utf8_lossy(&output.stdout).lines() {
+                    info!("| {}", line);
+                }
+                for line in String::from_utf8_lossy(&output.stderr).lines() {
+                    warn!("| {}", line);
+                }
+                if !output.status.success() {
+                    error!("Hook `{}` failed: {}", command, output.status);
+                }
+            }
+            Err(e) => error!("Hook `{}` cannot start: {}", command, e),
+        }
+    }
+}
+
+/// rewrite the reload trigger with the build time, so its content always changes
+pub fn write_reload_trigger(target: &Path) -> Result<(), String> {
+    let path = target.join(RELOAD_TRIGGER);
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    fs::write(&path, time.to_string())
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
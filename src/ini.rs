@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+/// 3dmigoto ini built from named groups of chunks,
+/// groups are placed into a template by `<group>` markers
+#[derive(Debug, Clone, Default)]
+pub struct IniConfig(HashMap<String, Vec<IniChunk>>);
+
+impl IniConfig {
+    pub fn new() -> IniConfig {
+        IniConfig(HashMap::new())
+    }
+    /// append `chunk` to the group `name`
+    pub fn insert(&mut self, name: &str, chunk: IniChunk) {
+        self.0.entry(name.to_string()).or_default().push(chunk);
+    }
+    /// fill `<group>` markers of `format_string`, markers of empty groups in `has` are removed
+    pub fn format(&self, format_string: &str, has: Vec<&str>) -> String {
+        let mut text = format_string.to_string();
+        for (name, chunks) in self.0.clone() {
+            text = text.replace(
+                &format!("<{}>", name),
+                &chunks
+                    .iter()
+                    .map(|chunks| chunks.format())
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            );
+        }
+        //pass maybe unused
+        for name in has {
+            text = text.replace(&format!("<{}>", name), "")
+        }
+        text
+    }
+}
+
+/// one `[section]` with its lines
+#[derive(Debug, Clone)]
+pub struct IniChunk {
+    name: String,
+    attrs: Vec<String>,
+}
+
+impl IniChunk {
+    pub fn new(name: &str) -> IniChunk {
+        IniChunk {
+            name: name.to_string(),
+            attrs: Vec::new(),
+        }
+    }
+    /// add a `name = value` line
+    pub fn attr(mut self, name: &str, value: &str) -> IniChunk {
+        self.attrs.push(format!("{} = {}", name, value));
+        self
+    }
+    /// add a raw line
+    pub fn push(mut self, text: &str) -> IniChunk {
+        self.attrs.push(text.to_string());
+        self
+    }
+    pub fn format(&self) -> String {
+        format!("[{}]\n{}\n", self.name, self.attrs.join("\n"))
+    }
+}
@@ -0,0 +1,36 @@
+//! HornyLoader build pipeline for Genshin Impact (GIMI) mods.
+//!
+//! - [`load::build_genshin_mod`] builds buffers and the `.ini` from an exported blender workspace
+//! - [`ini`] is the ini model the builder writes
+//! - [`package::extract`] unpacks a variant of a released mod from `package.7z`
+//! - [`dds::DDSParser`] converts png textures to dds in the background, [`dds::png_from_dds`] back
+//! - [`sync::sync_folder`] installs a build into a mods folder, [`hook`] runs post-build commands
+//! - [`event`] streams what a build does as json lines
+//!
+//! output is reported through the [`log`] crate, install a logger to see it.
+//!
+//! ```no_run
+//! use horny_loader::load::{build_genshin_mod, TextureFilter};
+//! use std::path::Path;
+//!
+//! // dev mode build of the workspace into `workspace/output`
+//! build_genshin_mod(
+//!     Path::new("workspace"),
+//!     "Furina".to_string(),
+//!     &TextureFilter::default(),
+//!     false,
+//!     String::new(),
+//! )?;
+//! # Ok::<(), String>(())
+//! ```
+
+pub mod dds;
+pub mod event;
+pub mod hook;
+pub mod ini;
+pub mod load;
+pub mod package;
+pub mod sync;
+
+/// separator of log sections
+pub const BREAK_LINE: &str = "===================================================";
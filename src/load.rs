@@ -1,135 +1,48 @@
+use image_dds::ImageFormat;
+use log::{error, info, log, warn, Level};
 use serde::{Deserialize, Serialize};
-use sevenz_rust::{Archive, BlockDecoder};
 use std::{
-    collections::HashMap,
-    env,
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
     fs::{self, File},
-    io::{BufRead, BufReader, Read, Write},
+    io::{BufRead, BufReader, Read},
     path::Path,
 };
 
-use crate::{list_and_choose, BREAK_LINE};
-
-const MOD_CONFIG: &str = "config.hl.json";
-
-pub fn main() -> Result<(), String> {
-    //load MOD_CONFIG
-    let path = Path::new(MOD_CONFIG);
-    let json = if path.exists() {
-        println!("Loading `{}`", MOD_CONFIG);
-        fs::read_to_string(path).map_err(|v| v.to_string())
-    } else {
-        Err(format!("`{}` Not Found", MOD_CONFIG))
-    }?;
-
-    let config: ModConfig = serde_json::from_str(&*json).unwrap();
-
-    println!("{}", BREAK_LINE);
-    println!("Name: {}", config.name);
-    println!("Options: {}", config.options.len());
-    println!("{}", BREAK_LINE);
-
-    let choose = list_and_choose("Operate", vec!["Choose Variants", "Exit"], true);
-    match choose {
-        0 => choose_variants(config),
-        1 => Ok(()),
-        _ => unreachable!(),
-    }
-}
-
-fn choose_variants(config: ModConfig) -> Result<(), String> {
-    let mut chooses = vec![0_usize; config.options.len()];
-    let exit_index = config.options.len();
-    loop {
-        let choose = list_and_choose("", get_list(&config, &chooses), false);
-
-        if choose == exit_index {
-            break;
-        } else if choose == exit_index + 1 {
-            // finished_index as exit_index + 1
-            let mut id = String::new();
-            for value in &chooses {
-                //break on over 16
-                id += &format!("{:x}", value);
-            }
-            println!("Loading {}", id);
-
-            let path = env::current_dir().unwrap();
-            let temp = path.join("temp");
-            extract(&path, &temp, &id);
-            build_genshin_mod(path.as_path(), config.name.clone(), true, id)?;
-            fs::remove_dir_all(temp).unwrap();
-        } else if choose < exit_index {
-            //variants
-            let (name, list) = config.options[choose].clone();
-            let variant = list_and_choose(format!("Changing `{}` Variants", name), list, false);
-            chooses[choose] = variant
-        } else if choose > exit_index + 1 {
-            unreachable!()
-        }
-    }
+use crate::{
+    dds::{self, ENCODE_FORMATS},
+    event::{self, Event},
+    ini::{IniChunk, IniConfig},
+    BREAK_LINE,
+};
 
-    fn get_list(config: &ModConfig, chooses: &Vec<usize>) -> Vec<String> {
-        let mut list: Vec<String> = config
-            .options
-            .iter()
-            .enumerate()
-            .map(|(index, (name, options))| format!("{}: {}", name, options[chooses[index]]))
-            .collect();
-        list.extend(["Exit".to_string(), "Finished".to_string()]);
-        list
-    }
+/// variant options of a released mod, read by Load Mod
+pub const MOD_CONFIG: &str = "config.hl.json";
 
-    Ok(())
-}
-
-fn extract(path: &Path, to: &Path, target: &str) {
-    let zip_path = path.join("package.7z");
-    let mut zip = File::open(zip_path).unwrap();
-    let len = zip.metadata().unwrap().len();
-    let arch = Archive::read(&mut zip, len, &[]).unwrap();
-    let folder_count = arch.folders.len();
-
-    println!("=Extract={}", BREAK_LINE);
-    for folder_index in 0..folder_count {
-        let forder_dec = BlockDecoder::new(folder_index, &arch, &[], &mut zip);
-        forder_dec
-            .for_each_entries(&mut |entry, reader| {
-                let name = entry.name();
-                if name.starts_with(&format!("{}/", target)) {
-                    println!("extract {}", entry.name());
-                    let dest = to.join(Path::new(entry.name()).file_name().unwrap());
-                    sevenz_rust::default_entry_extract_fn(entry, reader, &dest)?;
-                } else {
-                    std::io::copy(reader, &mut std::io::sink())?;
-                };
-                Ok(true)
-            })
-            .unwrap();
-    }
-    println!("========={}", BREAK_LINE);
-}
-///
-///
-///
-///
+/// build the mod in `path` into `path/output` (dev mode, `variants` empty)
+/// or into `path` itself for the chosen `variants` id,
+/// dev mode deletes `path/output` first, anything kept there is lost,
+/// vertex data is read from `path/temp` and textures from `path/assets`
 pub fn build_genshin_mod(
     path: &Path,
     name: String,
-    no_ramps: bool,
+    texture_filter: &TextureFilter,
+    placeholder_textures: bool,
     variants: String,
 ) -> Result<(), String> {
-    println!("Start build `{}`.", name);
-    println!("Basic Settings");
+    info!("Start build `{}`.", name);
+    info!("Basic Settings");
 
     let dev_mode = variants.is_empty();
-    println!("Dev Mode: {}", dev_mode);
+    info!("Dev Mode: {}", dev_mode);
+    info!("Texture Filter: {}", texture_filter);
+    info!("Placeholder Textures: {}", placeholder_textures);
 
     let assets_folder = path.join("assets");
-    println!("Assets Folder: {}", assets_folder.as_path().display());
+    info!("Assets Folder: {}", assets_folder.as_path().display());
 
     let temp_vertex_folder = path.join("temp");
-    println!(
+    info!(
         "Temp Vertex Folder: {}",
         temp_vertex_folder.as_path().display()
     );
@@ -139,47 +52,76 @@ pub fn build_genshin_mod(
     } else {
         path.to_path_buf()
     };
-    println!("Output Folder: {}", output_folder.as_path().display());
+    info!("Output Folder: {}", output_folder.as_path().display());
+    if dev_mode && output_folder.exists() {
+        //start clean, so the install sync sees exactly what this build produced
+        fs::remove_dir_all(&output_folder)
+            .map_err(|e| format!("Cannot clear {}: {}", output_folder.display(), e))?;
+    }
 
     let vertex_folder = output_folder.join("vertex");
-    println!("Vertex Folder: {}", vertex_folder.as_path().display());
+    info!("Vertex Folder: {}", vertex_folder.as_path().display());
 
-    create_output_folder(output_folder.as_path());
+    create_output_folder(output_folder.as_path())?;
 
-    println!("{}", BREAK_LINE);
-    println!("Reading hash.json in assets folder");
+    info!("{}", BREAK_LINE);
+    info!("Reading hash.json in assets folder");
     let component_list = load_hashes(&assets_folder, &name)?;
+
+    info!("Checking texture files");
+    let placeholder_folder = output_folder.join("assets");
+    let placeholders = check_textures(
+        &component_list,
+        &name,
+        texture_filter,
+        &assets_folder,
+        placeholder_textures.then_some(placeholder_folder.as_path()),
+        dev_mode,
+    )?;
     let mut ini_config = IniConfig::new();
 
     for component in component_list {
-        let component_name = component.component_name.unwrap_or_default();
-        let classifications = component.object_classifications.unwrap_or(vec![
-            "Head".to_string(),
-            "Body".to_string(),
-            "Extra".to_string(),
-        ]);
+        let component_name = component.component_name.clone().unwrap_or_default();
+        let classifications = component.classifications();
         let current_name = name.to_string() + &component_name;
         let has_blend_vb = !component.blend_vb.is_empty();
+        let texture_filter = component.texture_filter.as_ref().unwrap_or(texture_filter);
 
-        println!("====[{}]{}", current_name, BREAK_LINE);
+        info!("====[{}]{}", current_name, BREAK_LINE);
         if !component.draw_vb.is_empty() {
-            println!("Get stride");
+            info!("Get stride");
             let stride = {
                 let first_fmt =
                     temp_vertex_folder.join(format!("{}{}.fmt", current_name, classifications[0]));
-                let file = File::open(first_fmt).unwrap();
+                let file = File::open(&first_fmt)
+                    .map_err(|e| format!("Cannot open {}: {}", first_fmt.display(), e))?;
                 let reader = BufReader::new(file);
 
                 let mut stride = String::from("0");
                 for line in reader.lines() {
-                    let line = line.unwrap();
+                    let line =
+                        line.map_err(|e| format!("Cannot read {}: {}", first_fmt.display(), e))?;
                     let stride_position = line.find("stride:");
                     if let Some(pos) = stride_position {
                         stride = line[pos + 7..].trim().to_string();
                     }
                 }
 
-                stride.parse::<usize>().unwrap()
+                let stride = stride.parse::<usize>().map_err(|_| {
+                    format!("Invalid stride `{}` in {}", stride, first_fmt.display())
+                })?;
+                if stride == 0 {
+                    return Err(format!("No stride in {}", first_fmt.display()));
+                }
+                //blend buffers split 40 bytes of position and 32 of blend off each vertex
+                if has_blend_vb && stride < 72 {
+                    return Err(format!(
+                        "Stride {} in {} is too small for blend buffers, at least 72 is needed",
+                        stride,
+                        first_fmt.display()
+                    ));
+                }
+                stride
             };
 
             let mut offset: usize = 0;
@@ -196,22 +138,16 @@ pub fn build_genshin_mod(
             );
 
             let indexes_len = component.object_indexes.len();
-            let classifications_len = classifications.len();
-            let classifications_last = classifications.last().unwrap().clone();
 
             for i in 0..indexes_len {
-                let current_object = classifications.get(i).cloned().unwrap_or(format!(
-                    "{}{}",
-                    classifications_last,
-                    i + 2 - classifications_len
-                ));
+                let current_object = component.object_name(i);
 
-                println!("Load [{}]", current_object);
-                println!("Collecting VB");
+                info!("Load [{}]", current_object);
+                info!("Collecting VB");
 
                 let filename = &(current_name.clone() + &current_object);
                 let position_stride = if has_blend_vb {
-                    println!("Splitting VB by buffer type, merging body parts");
+                    info!("Splitting VB by buffer type, merging body parts");
                     collect_vb(
                         &temp_vertex_folder,
                         filename,
@@ -224,13 +160,11 @@ pub fn build_genshin_mod(
                     stride
                 };
 
-                println!("Collecting IB");
+                info!("Collecting IB");
                 let ib = collect_ib(&temp_vertex_folder, filename, offset)?;
 
-                println!("Write IB file");
-                let mut file =
-                    File::create(vertex_folder.join(format!("{}.ib", filename))).unwrap();
-                file.write_all(&ib).unwrap();
+                info!("Write IB file");
+                write_output(&vertex_folder.join(format!("{}.ib", filename)), &ib)?;
 
                 let mut ib_override = IniChunk::new(&format!("TextureOverride{}", filename))
                     .attr("hash", &component.ib)
@@ -255,46 +189,37 @@ pub fn build_genshin_mod(
                         .attr("filename", &format!("./vertex/{}.ib", filename)),
                 );
 
-                if position.len() % position_stride != 0 {
-                    eprint!("ERROR: VB buffer length does not match stride")
+                if !position.len().is_multiple_of(position_stride) {
+                    error!("VB buffer length does not match stride")
                 }
 
                 offset = position.len() / position_stride;
 
-                let textures = component
-                    .texture_hashes
-                    .clone()
-                    .and_then(|vec| Some(vec[i].clone()))
-                    .unwrap_or(vec![
-                        vec!["Diffuse".to_string(), ".dds".to_string(), "_".to_string()],
-                        vec!["LightMap".to_string(), ".dds".to_string(), "_".to_string()],
-                    ]);
+                let textures = component.textures(i);
 
-                println!("Copying texture files");
+                info!("Copying texture files");
                 let is_face = component_name == "Face";
 
                 let textures = if is_face {
                     let texture = textures[0].clone();
                     ini_config.insert("ib_override", ib_override);
                     ib_override =
-                        IniChunk::new(&format!("TextureOverride{}{}", filename, texture[0]))
-                            .attr("hash", &texture[2].to_string());
+                        IniChunk::new(&format!("TextureOverride{}{}", filename, texture.layout))
+                            .attr("hash", &texture.hash);
                     vec![texture]
                 } else {
                     textures
                 };
                 for (j, texture) in textures.iter().enumerate() {
-                    let layout_name = texture[0].clone();
-                    if no_ramps
-                        && vec!["ShadowRamp", "MetalMap", "DiffuseGuide"].contains(&&&*layout_name)
-                    {
+                    let layout_name = texture.layout.clone();
+                    if !texture_filter.keep(&layout_name) {
                         continue;
                     }
 
-                    let full_filename = format!("{}{}{}", filename, layout_name, texture[1]);
+                    let full_filename = format!("{}{}{}", filename, layout_name, texture.extension);
 
                     ib_override = ib_override.attr(
-                        &format!("ps-t{}", j),
+                        &format!("ps-t{}", texture.slot(j)),
                         &format!("Resource{}{}", filename, layout_name),
                     );
 
@@ -303,29 +228,29 @@ pub fn build_genshin_mod(
                         IniChunk::new(&format!("Resource{}{}", filename, layout_name))
                             .attr("filename", &format!("./assets/{}", full_filename)),
                     );
-                    if dev_mode {
-                        fs::copy(
-                            assets_folder.join(&full_filename),
-                            output_folder.join("assets").join(&full_filename),
-                        )
-                        .unwrap();
+                    if dev_mode && !placeholders.contains(&full_filename) {
+                        copy_output(
+                            &assets_folder.join(&full_filename),
+                            &output_folder.join("assets").join(&full_filename),
+                        )?;
                     }
                 }
                 ini_config.insert("ib_override", ib_override)
             }
             if !component.blend_vb.is_empty() {
-                println!("Writing merged buffer files");
-                let mut file =
-                    File::create(vertex_folder.join(format!("{}Position.buf", current_name)))
-                        .unwrap();
-                file.write_all(&position).unwrap();
-                let mut file =
-                    File::create(vertex_folder.join(format!("{}Blend.buf", current_name))).unwrap();
-                file.write_all(&blend).unwrap();
-                let mut file =
-                    File::create(vertex_folder.join(format!("{}Texcoord.buf", current_name)))
-                        .unwrap();
-                file.write_all(&texcoord).unwrap();
+                info!("Writing merged buffer files");
+                write_output(
+                    &vertex_folder.join(format!("{}Position.buf", current_name)),
+                    &position,
+                )?;
+                write_output(
+                    &vertex_folder.join(format!("{}Blend.buf", current_name)),
+                    &blend,
+                )?;
+                write_output(
+                    &vertex_folder.join(format!("{}Texcoord.buf", current_name)),
+                    &texcoord,
+                )?;
 
                 let chunk = IniChunk::new(&format!("TextureOverride{}Position", current_name))
                     .attr("hash", &component.position_vb)
@@ -392,10 +317,10 @@ pub fn build_genshin_mod(
                         ),
                 );
             } else {
-                let mut file =
-                    File::create(output_folder.join(format!("./vertex/{}.buf", current_name)))
-                        .unwrap();
-                file.write_all(&position).unwrap();
+                write_output(
+                    &output_folder.join(format!("./vertex/{}.buf", current_name)),
+                    &position,
+                )?;
 
                 let mut chunk = IniChunk::new(&format!("TextureOverride{}", current_name))
                     .attr("hash", &component.draw_vb)
@@ -416,24 +341,13 @@ pub fn build_genshin_mod(
         } else {
             let indexes_len = component.object_indexes.len();
             for i in 0..indexes_len {
-                let current_object = if i <= 2 {
-                    classifications[i].clone()
-                } else {
-                    format!("{}{}", classifications[2], i - 1)
-                };
+                let current_object = component.object_name(i);
 
                 let filename = &(current_name.clone() + &current_object);
-                println!("Texture override only on {}", current_object);
-                let textures = component
-                    .texture_hashes
-                    .clone()
-                    .and_then(|vec| Some(vec[i].clone()))
-                    .unwrap_or(vec![
-                        vec!["Diffuse".to_string(), ".dds".to_string(), "_".to_string()],
-                        vec!["LightMap".to_string(), ".dds".to_string(), "_".to_string()],
-                    ]);
-
-                println!("Copying texture files");
+                info!("Texture override only on {}", current_object);
+                let textures = component.textures(i);
+
+                info!("Copying texture files");
                 let is_face = component_name == "Face";
                 let textures = if is_face {
                     vec![textures[0].clone()]
@@ -442,21 +356,19 @@ pub fn build_genshin_mod(
                 };
 
                 for (j, texture) in textures.iter().enumerate() {
-                    let layout_name = texture[0].clone();
-                    if no_ramps
-                        && vec!["ShadowRamp", "MetalMap", "DiffuseGuide"].contains(&&&*layout_name)
-                    {
+                    let layout_name = texture.layout.clone();
+                    if !texture_filter.keep(&layout_name) {
                         continue;
                     }
 
-                    let full_filename = format!("{}{}{}", filename, layout_name, texture[1]);
+                    let full_filename = format!("{}{}{}", filename, layout_name, texture.extension);
 
                     ini_config.insert(
                         "ib_override",
                         IniChunk::new(&format!("TextureOverride{}{}", filename, layout_name))
-                            .attr("hash", &texture[2])
+                            .attr("hash", &texture.hash)
                             .attr(
-                                &format!("ps-t{}", j),
+                                &format!("ps-t{}", texture.slot(j)),
                                 &format!("Resource{}{}", filename, layout_name),
                             ),
                     );
@@ -465,19 +377,23 @@ pub fn build_genshin_mod(
                         IniChunk::new(&format!("Resource{}{}", filename, layout_name))
                             .attr("filename", &format!("./assets/{}", &full_filename)),
                     );
-                    if dev_mode {
-                      fs::copy(
-                          assets_folder.join(&full_filename),
-                          output_folder.join("assets").join(&full_filename),
-                      )
-                      .unwrap();
+                    if dev_mode && !placeholders.contains(&full_filename) {
+                        copy_output(
+                            &assets_folder.join(&full_filename),
+                            &output_folder.join("assets").join(&full_filename),
+                        )?;
                     }
                 }
             }
         }
+        event::emit(Event::ComponentCollected {
+            name: current_name,
+            component: component_name,
+            objects: component.object_indexes.len(),
+        });
     }
 
-    println!("collect finished");
+    info!("collect finished");
 
     if !variants.is_empty() {
         ini_config.insert(
@@ -513,7 +429,7 @@ pub fn build_genshin_mod(
         );
     }
     
-    println!("Generating .ini file");
+    info!("Generating .ini file");
 
     let ini_text = ini_config.format(
         ";Constants -------------------------
@@ -542,20 +458,52 @@ pub fn build_genshin_mod(
         ],
     );
 
-    fs::write(output_folder.join(format!("{}.ini", name)), ini_text).unwrap();
+    write_output(
+        &output_folder.join(format!("{}.ini", name)),
+        ini_text.as_bytes(),
+    )?;
+
+    Ok(())
+}
 
+/// write a build output file
+fn write_output(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    fs::write(path, bytes).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    event::emit(Event::FileWritten {
+        path: path.to_path_buf(),
+    });
     Ok(())
 }
 
+fn copy_output(from: &Path, to: &Path) -> Result<(), String> {
+    fs::copy(from, to).map_err(|e| format!("Cannot copy {}: {}", from.display(), e))?;
+    event::emit(Event::FileWritten {
+        path: to.to_path_buf(),
+    });
+    Ok(())
+}
+
+/// whole vertex or index buffer file of the temp folder, made of `stride` byte elements
+fn read_vertex(path: &Path, stride: usize) -> Result<Vec<u8>, String> {
+    let buff = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    if !buff.len().is_multiple_of(stride) {
+        return Err(format!(
+            "{} has {} bytes, which is not a multiple of {}, the file may be truncated",
+            path.display(),
+            buff.len(),
+            stride
+        ));
+    }
+    Ok(buff)
+}
+
 fn collect_vb(
     vertex_path: &Path,
     name: &str,
     bytes: (&mut Vec<u8>, &mut Vec<u8>, &mut Vec<u8>),
     stride: usize,
 ) -> Result<(), String> {
-    let mut file: File = File::open(vertex_path.join(name.to_string() + ".vb")).unwrap();
-    let mut buff = vec![];
-    file.read_to_end(&mut buff).unwrap();
+    let buff = read_vertex(&vertex_path.join(name.to_string() + ".vb"), stride)?;
     let buff_len = buff.len();
     let mut i = 0;
     while i < buff_len {
@@ -568,9 +516,7 @@ fn collect_vb(
 }
 
 fn collect_ib(vertex_path: &Path, name: &str, offset: usize) -> Result<Vec<u8>, String> {
-    let mut file = File::open(vertex_path.join(name.to_string() + ".ib")).unwrap();
-    let mut buff = vec![];
-    file.read_to_end(&mut buff).unwrap();
+    let buff = read_vertex(&vertex_path.join(name.to_string() + ".ib"), 4)?;
     let buff_len = buff.len();
     let mut ib = vec![];
     let mut i = 0;
@@ -582,8 +528,8 @@ fn collect_ib(vertex_path: &Path, name: &str, offset: usize) -> Result<Vec<u8>,
             .for_each(|v| value = (value << 8) + *v as usize);
         value += offset;
         let mut offset_bytes = [0_u8; 4];
-        for i in 0..4 {
-            *&mut offset_bytes[i] = (value % 256) as u8;
+        for byte in offset_bytes.iter_mut() {
+            *byte = (value % 256) as u8;
             value >>= 8
         }
 
@@ -599,9 +545,7 @@ fn collect_vb_single(
     bytes: &mut Vec<u8>,
     stride: usize,
 ) -> Result<(), String> {
-    let mut file = File::open(vertex_path.join(name.to_string() + ".vb")).unwrap();
-    let mut buff = vec![];
-    file.read_to_end(&mut buff).unwrap();
+    let buff = read_vertex(&vertex_path.join(name.to_string() + ".vb"), stride)?;
     let buff_len = buff.len();
     let mut i = 0;
     while i < buff_len {
@@ -611,118 +555,623 @@ fn collect_vb_single(
     Ok(())
 }
 
-fn load_hashes(assets_path: &Path, name: &str) -> Result<Vec<Component>, String> {
+/// read and validate `hash.json` (or the older `hash_info.json`) in `assets_path`
+pub fn load_hashes(assets_path: &Path, name: &str) -> Result<Vec<Component>, String> {
     let json_path = assets_path.join("hash.json");
     let older_json_path = assets_path.join("hash_info.json");
-    if json_path.exists() {
-        let file = File::open(json_path).unwrap();
-        serde_json::from_reader(file).map_err(|e| e.to_string())
+    let (components, root) = if json_path.exists() {
+        let file = File::open(&json_path)
+            .map_err(|e| format!("Cannot open {}: {}", json_path.display(), e))?;
+        let components: Vec<Component> =
+            serde_json::from_reader(file).map_err(|e| format!("Invalid hash.json: {}", e))?;
+        (components, "$".to_string())
     } else if older_json_path.exists() {
-        eprint!("[warning] Could not find hash.json in assets folder. fallback to hash_info.json");
-        let file = File::open(older_json_path).unwrap();
-        let mut object: HashMap<String, Component> = serde_json::from_reader(file).unwrap();
+        warn!("Could not find hash.json in assets folder. fallback to hash_info.json");
+        let file = File::open(&older_json_path)
+            .map_err(|e| format!("Cannot open {}: {}", older_json_path.display(), e))?;
+        let mut object: HashMap<String, Component> =
+            serde_json::from_reader(file).map_err(|e| format!("Invalid hash_info.json: {}", e))?;
         let component = object
             .remove(name)
             .ok_or(format!("Cannot find \"{}\" in hash_info.json", name))?;
-        Ok(vec![component])
+        (vec![component], format!("$.{}", name))
     } else {
-        Err("Cannot find hash information, check hash.json in assets".to_string())
+        return Err("Cannot find hash information, check hash.json in assets".to_string());
+    };
+
+    validate_components(&components, &root)?;
+    Ok(components)
+}
+
+/// check every component before building,
+/// errors are reported with the json path of the wrong field
+fn validate_components(components: &[Component], root: &str) -> Result<(), String> {
+    let mut errors = vec![];
+    for (index, component) in components.iter().enumerate() {
+        let path = if root == "$" {
+            format!("$[{}]", index)
+        } else {
+            root.to_string()
+        };
+        component.validate(&path, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "hash information has {} error(s)\n{}",
+            errors.len(),
+            errors.join("\n")
+        ))
+    }
+}
+
+fn is_hash(value: &str) -> bool {
+    value.len() == 8 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// texture file names written into the mod with their format hint, grouped by object
+fn texture_files(
+    components: &[Component],
+    name: &str,
+    texture_filter: &TextureFilter,
+) -> Vec<Vec<(String, Option<ImageFormat>)>> {
+    let mut objects = vec![];
+    for component in components {
+        let texture_filter = component.texture_filter.as_ref().unwrap_or(texture_filter);
+        let current_name = name.to_string() + component.component_name.as_deref().unwrap_or("");
+        let is_face = component.component_name.as_deref() == Some("Face");
+        for i in 0..component.object_indexes.len() {
+            let filename = current_name.clone() + &component.object_name(i);
+            let mut textures = component.textures(i);
+            if is_face {
+                textures.truncate(1);
+            }
+            objects.push(
+                textures
+                    .iter()
+                    .filter(|texture| texture_filter.keep(&texture.layout))
+                    .map(|texture| {
+                        let file = format!("{}{}{}", filename, texture.layout, texture.extension);
+                        (file, texture.format.as_deref().and_then(dds::encode_format))
+                    })
+                    .collect(),
+            );
+        }
+    }
+    objects
+}
+
+/// names (without extension) of the dds textures the mod in `path` uses,
+/// with the format hint of their texture slot
+pub fn referenced_textures(
+    path: &Path,
+    name: &str,
+    texture_filter: &TextureFilter,
+) -> Result<HashMap<String, Option<ImageFormat>>, String> {
+    let components = load_hashes(&path.join("assets"), name)?;
+    Ok(texture_files(&components, name, texture_filter)
+        .into_iter()
+        .flatten()
+        .filter_map(|(file, format)| {
+            let file = Path::new(&file);
+            let is_dds = file
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("dds"));
+            is_dds.then(|| {
+                let name = file.file_stem().unwrap().to_string_lossy().to_string();
+                (name, format)
+            })
+        })
+        .collect())
+}
+
+/// find textures referenced by the components but missing in the assets folder,
+/// writes placeholders into `placeholder_folder` if given,
+/// otherwise missing textures are an error in dev mode and a warning in load mode
+fn check_textures(
+    components: &[Component],
+    name: &str,
+    texture_filter: &TextureFilter,
+    assets_folder: &Path,
+    placeholder_folder: Option<&Path>,
+    dev_mode: bool,
+) -> Result<HashSet<String>, String> {
+    let mut missing = vec![];
+    for files in texture_files(components, name, texture_filter) {
+        let files: Vec<String> = files.into_iter().map(|(file, _)| file).collect();
+        for file in &files {
+            if !assets_folder.join(file).is_file() {
+                missing.push((file.clone(), files.clone()));
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    //load mode only references the files in the ini, the mod still loads without them
+    let level = if dev_mode || placeholder_folder.is_some() {
+        Level::Error
+    } else {
+        Level::Warn
+    };
+    log!(
+        level,
+        "Missing {} texture(s) in assets folder",
+        missing.len()
+    );
+    for (file, _) in &missing {
+        log!(level, "- {}", file);
     }
+
+    let Some(placeholder_folder) = placeholder_folder else {
+        if !dev_mode {
+            return Ok(HashSet::new());
+        }
+        return Err(format!(
+            "Missing texture(s) in assets folder: {}",
+            missing
+                .iter()
+                .map(|(file, _)| file.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    };
+
+    let mut placeholders = HashSet::new();
+    for (file, siblings) in missing {
+        // same size as another texture of the object
+        let (width, height) = siblings
+            .iter()
+            .find_map(|sibling| texture_size(&assets_folder.join(sibling)))
+            .unwrap_or((1024, 1024));
+        info!("Generate placeholder {} ({}x{})", file, width, height);
+        let path = placeholder_folder.join(&file);
+        write_placeholder(&path, width, height)?;
+        event::emit(Event::FileWritten { path });
+        placeholders.insert(file);
+    }
+    Ok(placeholders)
 }
 
-fn create_output_folder(output: &Path) {
+fn texture_size(path: &Path) -> Option<(u32, u32)> {
+    if !path.is_file() {
+        return None;
+    }
+    let is_dds = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("dds"));
+    if is_dds {
+        // read width and height from the dds header
+        let mut header = [0_u8; 20];
+        File::open(path).ok()?.read_exact(&mut header).ok()?;
+        if &header[0..4] != b"DDS " {
+            return None;
+        }
+        let height = u32::from_le_bytes(header[12..16].try_into().unwrap());
+        let width = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        Some((width, height))
+    } else {
+        image::image_dimensions(path).ok()
+    }
+}
+
+/// magenta checkerboard, easy to spot in game
+fn write_placeholder(path: &Path, width: u32, height: u32) -> Result<(), String> {
+    // block compression needs multiple of 4
+    let width = width.max(4).next_multiple_of(4);
+    let height = height.max(4).next_multiple_of(4);
+    let cell = (width.max(height) / 16).max(4);
+    let image = image::RgbaImage::from_fn(width, height, |x, y| {
+        if (x / cell + y / cell) & 1 == 0 {
+            image::Rgba([255, 0, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 255])
+        }
+    });
+
+    let is_dds = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("dds"));
+    if is_dds {
+        let dds = image_dds::dds_from_image(
+            &image,
+            image_dds::ImageFormat::BC7Srgb,
+            image_dds::Quality::Fast,
+            image_dds::Mipmaps::Disabled,
+        )
+        .map_err(|e| format!("Cannot encode placeholder {}: {}", path.display(), e))?;
+        let mut writer = std::io::BufWriter::new(
+            File::create(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?,
+        );
+        dds.write(&mut writer)
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    } else {
+        image
+            .save(path)
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+}
+
+fn create_output_folder(output: &Path) -> Result<(), String> {
+    let create = |path: &Path| {
+        fs::create_dir(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))
+    };
     if !output.exists() {
-        println!("Generate mod folder");
-        fs::create_dir(output).unwrap();
+        info!("Generate mod folder");
+        create(output)?;
     }
 
     let path = output.join("vertex");
     if !path.exists() {
-        println!("Generate mod/vertex folder");
-        fs::create_dir(path).unwrap();
+        info!("Generate mod/vertex folder");
+        create(&path)?;
     }
 
     let path = output.join("assets");
     if !path.exists() {
-        println!("Generate mod/assets folder");
-        fs::create_dir(path).unwrap();
+        info!("Generate mod/assets folder");
+        create(&path)?;
     }
+    Ok(())
+}
+
+/// `config.hl.json` of a released mod
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModConfig {
+    pub name: String,
+    /// option name and its variants, the chosen indexes in hex make the variant id
+    pub options: Vec<(String, Vec<String>)>,
+    #[serde(default)]
+    pub texture_filter: Option<TextureFilter>,
+    #[serde(default)]
+    pub placeholder_textures: bool,
 }
 
-#[derive(Debug, Clone)]
-struct IniConfig(HashMap<String, Vec<IniChunk>>);
+/// which texture layouts are written into the mod,
+/// `{"deny": [...]}` skips the listed layouts, `{"allow": [...]}` keeps only them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextureFilter {
+    Allow(Vec<String>),
+    Deny(Vec<String>),
+}
 
-impl IniConfig {
-    pub fn new() -> IniConfig {
-        IniConfig(HashMap::new())
+impl TextureFilter {
+    pub fn keep(&self, layout: &str) -> bool {
+        match self {
+            TextureFilter::Allow(list) => list.iter().any(|name| name == layout),
+            TextureFilter::Deny(list) => !list.iter().any(|name| name == layout),
+        }
     }
-    pub fn insert(&mut self, name: &str, chunk: IniChunk) {
-        self.0.entry(name.to_string()).or_default().push(chunk);
+}
+
+impl Default for TextureFilter {
+    /// skip ramps, same as before the filter was configurable
+    fn default() -> Self {
+        TextureFilter::Deny(
+            ["ShadowRamp", "MetalMap", "DiffuseGuide"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        )
     }
-    pub fn format(&self, format_string: &str, has: Vec<&str>) -> String {
-        let mut text = format_string.to_string();
-        for (name, chunks) in self.0.clone() {
-            text = text.replace(
-                &format!("<{}>", name),
-                &chunks
-                    .iter()
-                    .map(|chunks| chunks.format())
-                    .collect::<Vec<_>>()
-                    .join("\n\n"),
-            );
-        }
-        //pass maybe unused
-        for name in has {
-            text = text.replace(&format!("<{}>", name), "")
+}
+
+impl Display for TextureFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mode, list) = match self {
+            TextureFilter::Allow(list) => ("Allow", list),
+            TextureFilter::Deny(list) => ("Deny", list),
+        };
+        if list.is_empty() {
+            write!(f, "{} <NONE>", mode)
+        } else {
+            write!(f, "{} {}", mode, list.join(", "))
         }
-        text
     }
 }
 
-#[derive(Debug, Clone)]
-struct IniChunk {
-    name: String,
-    attrs: Vec<String>,
+/// one entry of `hash.json` in the assets folder
+#[derive(Debug, Deserialize)]
+pub struct Component {
+    pub component_name: Option<String>,
+    pub root_vs: Option<String>,
+    pub draw_vb: String,
+    pub position_vb: String,
+    pub blend_vb: String,
+    pub texcoord_vb: String,
+    pub ib: String,
+    pub object_indexes: Vec<usize>,
+    pub object_classifications: Option<Vec<String>>,
+    pub texture_hashes: Option<Vec<Vec<TextureSlot>>>,
+    pub texture_filter: Option<TextureFilter>,
+    pub first_vs: String,
 }
 
-impl IniChunk {
-    pub fn new(name: &str) -> IniChunk {
-        IniChunk {
-            name: name.to_string(),
-            attrs: Vec::new(),
+impl Component {
+    /// object names, Head, Body and Extra if not given
+    pub fn classifications(&self) -> Vec<String> {
+        self.object_classifications.clone().unwrap_or(vec![
+            "Head".to_string(),
+            "Body".to_string(),
+            "Extra".to_string(),
+        ])
+    }
+    /// name of the object at `index`, extra objects are numbered after the last classification
+    pub fn object_name(&self, index: usize) -> String {
+        let classifications = self.classifications();
+        if self.draw_vb.is_empty() {
+            if index <= 2 {
+                classifications[index].clone()
+            } else {
+                format!("{}{}", classifications[2], index - 1)
+            }
+        } else {
+            classifications.get(index).cloned().unwrap_or(format!(
+                "{}{}",
+                classifications.last().unwrap(),
+                index + 2 - classifications.len()
+            ))
         }
     }
-    pub fn attr(mut self, name: &str, value: &str) -> IniChunk {
-        self.attrs.push(format!("{} = {}", name, value));
-        self
+    /// textures of the object at `index`, Diffuse and LightMap if not given
+    pub fn textures(&self, index: usize) -> Vec<TextureSlot> {
+        self.texture_hashes
+            .as_ref()
+            .map(|vec| vec[index].clone())
+            .unwrap_or(vec![
+                TextureSlot::new("Diffuse", ".dds", "_"),
+                TextureSlot::new("LightMap", ".dds", "_"),
+            ])
     }
-    pub fn push(mut self, text: &str) -> IniChunk {
-        self.attrs.push(text.to_string());
-        self
+    fn validate(&self, path: &str, errors: &mut Vec<String>) {
+        let mut check_hash = |field: &str, value: &str, required: bool| {
+            if value.is_empty() {
+                if required {
+                    errors.push(format!("`{}.{}`: required hash is empty", path, field));
+                }
+            } else if !is_hash(value) {
+                errors.push(format!(
+                    "`{}.{}`: expected 8 hex digits, found \"{}\"",
+                    path, field, value
+                ));
+            }
+        };
+
+        let has_draw_vb = !self.draw_vb.is_empty();
+        let has_blend_vb = !self.blend_vb.is_empty();
+        check_hash("draw_vb", &self.draw_vb, false);
+        check_hash("ib", &self.ib, has_draw_vb);
+        check_hash("blend_vb", &self.blend_vb, false);
+        check_hash("position_vb", &self.position_vb, has_blend_vb);
+        check_hash("texcoord_vb", &self.texcoord_vb, has_blend_vb);
+        check_hash("first_vs", &self.first_vs, false);
+        if let Some(root_vs) = &self.root_vs {
+            check_hash("root_vs", root_vs, false);
+        }
+
+        if self.object_indexes.is_empty() {
+            errors.push(format!("`{}.object_indexes`: is empty", path));
+        }
+
+        if let Some(classifications) = &self.object_classifications {
+            if classifications.is_empty() {
+                errors.push(format!("`{}.object_classifications`: is empty", path));
+            } else if !has_draw_vb
+                && classifications.len() < 3
+                && self.object_indexes.len() > classifications.len()
+            {
+                errors.push(format!(
+                    "`{}.object_classifications`: texture override only component needs at least 3 classifications for {} objects",
+                    path,
+                    self.object_indexes.len()
+                ));
+            }
+        }
+
+        let Some(texture_hashes) = &self.texture_hashes else {
+            return;
+        };
+
+        if texture_hashes.len() != self.object_indexes.len() {
+            errors.push(format!(
+                "`{}.texture_hashes`: has {} entries, but `object_indexes` has {}",
+                path,
+                texture_hashes.len(),
+                self.object_indexes.len()
+            ));
+        }
+
+        // texture hashes are only written to the ini on texture override only components,
+        // faces only use the first texture
+        let is_face = self.component_name.as_deref() == Some("Face");
+        for (i, textures) in texture_hashes.iter().enumerate() {
+            if textures.is_empty() {
+                errors.push(format!("`{}.texture_hashes[{}]`: is empty", path, i));
+            }
+            let mut slots = HashSet::new();
+            for (j, texture) in textures.iter().enumerate() {
+                let texture_path = format!("{}.texture_hashes[{}][{}]", path, i, j);
+                if texture.layout.is_empty() {
+                    errors.push(format!("`{}.layout`: layout name is empty", texture_path));
+                }
+                if !texture.extension.starts_with('.') || texture.extension.len() < 2 {
+                    errors.push(format!(
+                        "`{}.extension`: expected a file extension like \".dds\", found \"{}\"",
+                        texture_path, texture.extension
+                    ));
+                }
+                if let Some(format) = &texture.format {
+                    if dds::encode_format(format).is_none() {
+                        errors.push(format!(
+                            "`{}.format`: unknown dds format \"{}\", expected one of {}",
+                            texture_path,
+                            format,
+                            ENCODE_FORMATS.map(|format| format.to_string()).join(", ")
+                        ));
+                    }
+                }
+                let need_texture_hash = if is_face { j == 0 } else { !has_draw_vb };
+                let placeholder = texture.hash == "_" && !need_texture_hash;
+                if !placeholder && !is_hash(&texture.hash) {
+                    errors.push(format!(
+                        "`{}.hash`: expected 8 hex digits, found \"{}\"",
+                        texture_path, texture.hash
+                    ));
+                }
+                if !slots.insert(texture.slot(j)) {
+                    errors.push(format!(
+                        "`{}.slot`: ps-t{} is already used by another texture",
+                        texture_path,
+                        texture.slot(j)
+                    ));
+                }
+            }
+        }
     }
-    pub fn format(&self) -> String {
-        format!("[{}]\n{}\n", self.name, self.attrs.join("\n"))
+}
+
+/// texture bound to `ps-t{slot}`,
+/// also accepts the old `[layout, extension, hash]` array
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "TextureSlotDef")]
+pub struct TextureSlot {
+    pub slot: Option<usize>,
+    pub layout: String,
+    pub extension: String,
+    pub hash: String,
+    /// dds format like `BC7Unorm`, overrides the dds policy of dev mode
+    pub format: Option<String>,
+}
+
+impl TextureSlot {
+    pub fn new(layout: &str, extension: &str, hash: &str) -> TextureSlot {
+        TextureSlot {
+            slot: None,
+            layout: layout.to_string(),
+            extension: extension.to_string(),
+            hash: hash.to_string(),
+            format: None,
+        }
+    }
+    /// explicit slot, or the position in the list
+    pub fn slot(&self, index: usize) -> usize {
+        self.slot.unwrap_or(index)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ModConfig {
-    name: String,
-    options: Vec<(String, Vec<String>)>,
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureSlotDef {
+    Array(Vec<String>),
+    Object {
+        slot: Option<usize>,
+        layout: String,
+        #[serde(default = "default_texture_extension")]
+        extension: String,
+        #[serde(default = "default_texture_hash")]
+        hash: String,
+        format: Option<String>,
+    },
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-struct Component {
-    component_name: Option<String>,
-    root_vs: Option<String>,
-    draw_vb: String,
-    position_vb: String,
-    blend_vb: String,
-    texcoord_vb: String,
-    ib: String,
-    object_indexes: Vec<usize>,
-    object_classifications: Option<Vec<String>>,
-    texture_hashes: Option<Vec<Vec<Vec<String>>>>,
-    first_vs: String,
+impl From<TextureSlotDef> for TextureSlot {
+    fn from(def: TextureSlotDef) -> Self {
+        match def {
+            TextureSlotDef::Array(array) => {
+                // missing elements are left empty and reported by `Component::validate`
+                let get = |i: usize| array.get(i).cloned().unwrap_or_default();
+                TextureSlot::new(&get(0), &get(1), &get(2))
+            }
+            TextureSlotDef::Object {
+                slot,
+                layout,
+                extension,
+                hash,
+                format,
+            } => TextureSlot {
+                slot,
+                layout,
+                extension,
+                hash,
+                format,
+            },
+        }
+    }
+}
+
+fn default_texture_extension() -> String {
+    ".dds".to_string()
+}
+
+fn default_texture_hash() -> String {
+    "_".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(json: &str, root: &str) -> Vec<String> {
+        let components: Vec<Component> = serde_json::from_str(json).unwrap();
+        match validate_components(&components, root) {
+            Ok(()) => vec![],
+            Err(error) => error.lines().skip(1).map(str::to_string).collect(),
+        }
+    }
+
+    const BODY: &str = r#"{
+        "component_name": "Body",
+        "draw_vb": "aaaaaaaa",
+        "position_vb": "bbbbbbbb",
+        "blend_vb": "cccccccc",
+        "texcoord_vb": "dddddddd",
+        "ib": "eeeeeeee",
+        "object_indexes": [0, 10],
+        "first_vs": "ffffffff",
+        "texture_hashes": [
+            [["Diffuse", ".dds", "_"], ["LightMap", ".dds", "_"]],
+            [{"layout": "Diffuse", "format": "BC7Unorm"}]
+        ]
+    }"#;
+
+    #[test]
+    fn valid_component() {
+        assert_eq!(errors(&format!("[{}]", BODY), "$"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn errors_name_the_json_path() {
+        let json = format!(
+            "[{}, {}]",
+            BODY,
+            BODY.replace("\"eeeeeeee\"", "\"\"")
+                .replace("\"LightMap\", \".dds\"", "\"LightMap\", \"dds\"")
+                .replace("BC7Unorm", "BC9")
+        );
+        assert_eq!(
+            errors(&json, "$"),
+            vec![
+                "`$[1].ib`: required hash is empty".to_string(),
+                "`$[1].texture_hashes[0][1].extension`: expected a file extension like \".dds\", found \"dds\"".to_string(),
+                format!(
+                    "`$[1].texture_hashes[1][0].format`: unknown dds format \"BC9\", expected one of {}",
+                    ENCODE_FORMATS.map(|format| format.to_string()).join(", ")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_of_hash_info_use_the_character_key() {
+        let json = format!("[{}]", BODY.replace("[0, 10]", "[0]"));
+        assert_eq!(
+            errors(&json, "$.Char"),
+            vec!["`$.Char.texture_hashes`: has 2 entries, but `object_indexes` has 1".to_string()]
+        );
+    }
 }
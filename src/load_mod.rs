@@ -0,0 +1,106 @@
+use horny_loader::{
+    event::{self, Event},
+    load::{build_genshin_mod, ModConfig, MOD_CONFIG},
+    package, BREAK_LINE,
+};
+use log::info;
+use std::{env, fs, path::Path, time::Instant};
+
+use crate::list_and_choose;
+
+/// Load Mod: choose the variants of a released mod and build them
+pub fn main() -> Result<(), String> {
+    //load MOD_CONFIG
+    let path = Path::new(MOD_CONFIG);
+    let json = if path.exists() {
+        info!("Loading `{}`", MOD_CONFIG);
+        fs::read_to_string(path).map_err(|v| v.to_string())
+    } else {
+        Err(format!("`{}` Not Found", MOD_CONFIG))
+    }?;
+
+    let config: ModConfig =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid `{}`: {}", MOD_CONFIG, e))?;
+
+    info!("{}", BREAK_LINE);
+    info!("Name: {}", config.name);
+    info!("Options: {}", config.options.len());
+    info!("{}", BREAK_LINE);
+
+    let choose = list_and_choose("Operate", vec!["Choose Variants", "Exit"], true);
+    match choose {
+        0 => choose_variants(config),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+fn choose_variants(config: ModConfig) -> Result<(), String> {
+    let mut chooses = vec![0_usize; config.options.len()];
+    let exit_index = config.options.len();
+    loop {
+        let choose = list_and_choose("", get_list(&config, &chooses), false);
+
+        if choose == exit_index {
+            break;
+        } else if choose == exit_index + 1 {
+            // finished_index as exit_index + 1
+            let mut id = String::new();
+            for value in &chooses {
+                //break on over 16
+                id += &format!("{:x}", value);
+            }
+            info!("Loading {}", id);
+
+            let path = env::current_dir().unwrap();
+            let temp = path.join("temp");
+            package::extract(&path, &temp, &id)?;
+            let texture_filter = config.texture_filter.clone().unwrap_or_default();
+            let started = Instant::now();
+            event::emit(Event::BuildStarted {
+                name: config.name.clone(),
+                path: path.clone(),
+                dev_mode: false,
+            });
+            let result = build_genshin_mod(
+                path.as_path(),
+                config.name.clone(),
+                &texture_filter,
+                config.placeholder_textures,
+                id,
+            );
+            event::emit(Event::BuildFinished {
+                name: config.name.clone(),
+                success: result.is_ok(),
+                error: result.clone().err(),
+                duration_ms: started.elapsed().as_millis() as u64,
+                targets: vec![path.clone()],
+            });
+            //clean up even when the build failed, the next extract starts from an empty temp
+            let cleanup = fs::remove_dir_all(&temp)
+                .map_err(|e| format!("Cannot remove {}: {}", temp.display(), e));
+            result?;
+            cleanup?;
+        } else if choose < exit_index {
+            //variants
+            let (name, list) = config.options[choose].clone();
+            let variant = list_and_choose(format!("Changing `{}` Variants", name), list, false);
+            chooses[choose] = variant
+        } else if choose > exit_index + 1 {
+            unreachable!()
+        }
+    }
+
+    fn get_list(config: &ModConfig, chooses: &[usize]) -> Vec<String> {
+        let mut list: Vec<String> = config
+            .options
+            .iter()
+            .enumerate()
+            .map(|(index, (name, options))| format!("{}: {}", name, options[chooses[index]]))
+            .collect();
+        list.extend(["Exit".to_string(), "Finished".to_string()]);
+        list
+    }
+
+    Ok(())
+}
@@ -0,0 +1,248 @@
+use horny_loader::event::{self, Event};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// lines kept for the terminal ui
+const BUFFER_LINES: usize = 1000;
+
+/// log file in the workspace, rotated to `hl.log.1` .. `hl.log.3`
+pub const LOG_FILE: &str = "hl.log";
+const LOG_FILE_SIZE: u64 = 1024 * 1024;
+const LOG_FILE_KEEP: usize = 3;
+
+/// log lines captured while the terminal ui owns the screen
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<(Level, String)>>>);
+
+impl LogBuffer {
+    fn push(&self, level: Level, message: String) {
+        let mut lines = self.0.lock().unwrap();
+        for line in message.lines() {
+            lines.push_back((level, line.to_string()));
+        }
+        while lines.len() > BUFFER_LINES {
+            lines.pop_front();
+        }
+    }
+    pub fn lines(&self) -> Vec<(Level, String)> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// console levels, e.g. `info,dds=debug,load=warn`
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    pub level: LevelFilter,
+    /// levels by module target: `load`, `dev`, `dds`, `sync`, `hook`
+    pub targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    pub const fn new(level: LevelFilter) -> LogFilter {
+        LogFilter {
+            level,
+            targets: Vec::new(),
+        }
+    }
+    /// add a spec like `debug` or `dds=trace,load=warn`
+    pub fn parse(&mut self, spec: &str) -> Result<(), String> {
+        for part in spec.split(',').map(|part| part.trim()) {
+            if part.is_empty() {
+                continue;
+            }
+            let parse_level = |level: &str| {
+                level
+                    .parse::<LevelFilter>()
+                    .map_err(|_| format!("Unknown log level `{}`", level))
+            };
+            match part.split_once('=') {
+                Some((target, level)) => {
+                    let level = parse_level(level)?;
+                    self.targets.retain(|(name, _)| name != target);
+                    self.targets.push((target.to_string(), level));
+                }
+                None => self.level = parse_level(part)?,
+            }
+        }
+        Ok(())
+    }
+    fn level(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(name, _)| name == target)
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+    fn max(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, |max, level| max.max(level))
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> std::io::Result<LogFile> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let len = file.metadata()?.len();
+        Ok(LogFile { path, file, len })
+    }
+    fn write(&mut self, line: &str) {
+        if self.len + line.len() as u64 > LOG_FILE_SIZE {
+            self.rotate();
+        }
+        if self.file.write_all(line.as_bytes()).is_ok() {
+            self.len += line.len() as u64;
+        }
+    }
+    fn rotate(&mut self) {
+        let rotated = |i: usize| PathBuf::from(format!("{}.{}", self.path.display(), i));
+        for i in (1..LOG_FILE_KEEP).rev() {
+            let _ = fs::rename(rotated(i), rotated(i + 1));
+        }
+        let _ = fs::rename(&self.path, rotated(1));
+        if let Ok(file) = LogFile::open(self.path.clone()) {
+            *self = file;
+        }
+    }
+}
+
+struct Logger {
+    filter: Mutex<LogFilter>,
+    capture: Mutex<Option<LogBuffer>>,
+    file: Mutex<Option<LogFile>>,
+}
+
+static LOGGER: Logger = Logger {
+    filter: Mutex::new(LogFilter::new(LevelFilter::Info)),
+    capture: Mutex::new(None),
+    file: Mutex::new(None),
+};
+
+/// module name of a record, `horny_loader::dds` is `dds`
+fn target<'a>(metadata: &Metadata<'a>) -> &'a str {
+    let target = metadata.target();
+    let target = target
+        .strip_prefix(env!("CARGO_CRATE_NAME"))
+        .map(|target| target.trim_start_matches(':'))
+        .unwrap_or(target);
+    target.split("::").next().unwrap_or(target)
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        //warnings and errors are always events
+        let console = metadata.level() <= self.filter.lock().unwrap().level(target(metadata));
+        let file = metadata.level() <= Level::Debug && self.file.lock().unwrap().is_some();
+        console || file || metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        let target = target(record.metadata());
+        let level = record.level();
+        let console = level <= self.filter.lock().unwrap().level(target);
+        let message = record.args().to_string();
+
+        //the file keeps debug lines for bug reports, whatever the console shows
+        if let Some(file) = &mut *self.file.lock().unwrap() {
+            if console || level <= Level::Debug {
+                let time = timestamp();
+                for line in message.lines() {
+                    file.write(&format!("{} {:<5} [{}] {}\n", time, level, target, line));
+                }
+            }
+        }
+
+        match level {
+            Level::Error => event::emit(Event::Error {
+                target: target.to_string(),
+                message: message.clone(),
+            }),
+            Level::Warn => event::emit(Event::Warning {
+                target: target.to_string(),
+                message: message.clone(),
+            }),
+            _ => {}
+        }
+
+        if !console {
+            return;
+        }
+        match &*self.capture.lock().unwrap() {
+            Some(buffer) => buffer.push(level, message),
+            None if level <= Level::Warn || event::to_stdout() => eprintln!("{}", message),
+            None => println!("{}", message),
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &mut *self.file.lock().unwrap() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+/// start logging to the console, and to `hl.log` in `workspace` if set
+pub fn init(filter: LogFilter, workspace: Option<&Path>) {
+    let mut max = filter.max().max(LevelFilter::Warn);
+    if let Some(workspace) = workspace {
+        match LogFile::open(workspace.join(LOG_FILE)) {
+            Ok(file) => {
+                *LOGGER.file.lock().unwrap() = Some(file);
+                max = max.max(LevelFilter::Debug);
+            }
+            Err(e) => eprintln!("Cannot open {}: {}", LOG_FILE, e),
+        }
+    }
+    *LOGGER.filter.lock().unwrap() = filter;
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(max);
+}
+
+/// send log lines into `buffer` instead of the terminal, `None` prints again
+pub fn capture(buffer: Option<LogBuffer>) {
+    *LOGGER.capture.lock().unwrap() = buffer;
+}
+
+/// utc time as `2024-01-31 12:00:00`
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    //days to civil date, from howard hinnant's algorithm
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
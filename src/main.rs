@@ -1,34 +1,104 @@
+use horny_loader::{event, BREAK_LINE};
+use log::{error, LevelFilter};
+use logger::LogFilter;
+use rfd::FileDialog;
 use std::thread;
 use std::time::Duration;
 use std::{
+    env,
     fmt::Display,
+    fs,
     io::{self, Write},
+    path::{Path, PathBuf},
+    process,
 };
 
-const BREAK_LINE: &'static str = "===================================================";
+/// menu and prompt text, kept off stdout while it carries events
+macro_rules! prompt {
+    ($($arg:tt)*) => {
+        if horny_loader::event::to_stdout() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
 
+mod control;
 mod dev;
-mod load;
+mod extract;
+mod load_mod;
+mod logger;
+mod tui;
+
+const USAGE: &str = "Usage: horny_loader [load|dev|extract] [--<setting> <value>]...
+  dev settings can also be set by `HL_DEV_<SETTING>` env vars, e.g. HL_DEV_HOT_RELOAD=true
+  --profile <name>        dev profile to use
+  --name <name>           character name
+  --from-path <folder>    mod path
+  --targets <a,b>         install targets
+  --control <address>     dev control server, a localhost port or `unix:<socket>`
+  --<setting> <value>     any other key of dev.hl.json, as json or plain text
+  HL_NO_DIALOG=1          type folder paths instead of opening a dialog
+logging:
+  -v, --verbose           debug output, twice for trace
+  -q, --quiet             warnings and errors only
+  --log <spec>            levels by module, e.g. `info,dds=debug,load=warn` (or HL_LOG)
+  --log-file              also write `hl.log` in the current folder (or HL_LOG_FILE=1)
+  --events <path>         json lines of build events, `-` for stdout (or HL_EVENTS), see EVENTS.md";
 
 fn main() {
+    init_output().unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    //run one function directly, for scripts
+    let command = args().first().cloned().unwrap_or_default();
+    let function = match command.as_str() {
+        "load" => Some(load_mod::main as fn() -> Result<(), String>),
+        "dev" => Some(dev::main as fn() -> Result<(), String>),
+        "extract" => Some(extract::main as fn() -> Result<(), String>),
+        "-h" | "--help" | "help" => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => None,
+    };
+    if let Some(function) = function {
+        if let Err(info) = function() {
+            error!(
+                "==Error============================================\n{}\n{}",
+                info, BREAK_LINE
+            );
+            process::exit(1);
+        }
+        return;
+    }
+
     loop {
-        println!("|HornyLoader|");
-        println!("A Genshin Mod Tools for build & load");
-        println!("Power By xiao-e-yun (https://github.com/xiao-e-yun)");
-        println!("{}", BREAK_LINE);
+        prompt!("|HornyLoader|");
+        prompt!("A Genshin Mod Tools for build & load");
+        prompt!("Power By xiao-e-yun (https://github.com/xiao-e-yun)");
+        prompt!("{}", BREAK_LINE);
 
-        let index = list_and_choose(&"Functions", vec!["Load Mod", "Dev Mode", "Exit"], true);
+        let index = list_and_choose(
+            "Functions",
+            vec!["Load Mod", "Dev Mode", "Extract Textures", "Exit"],
+            true,
+        );
 
         match index {
-            0 => load::main(),
+            0 => load_mod::main(),
             1 => dev::main(),
-            2 => {
+            2 => extract::main(),
+            3 => {
                 break;
             }
             _ => unreachable!(),
         }
         .unwrap_or_else(|info| {
-            eprintln!(
+            error!(
                 "==Error============================================\n{}\n{}",
                 info, BREAK_LINE
             )
@@ -41,15 +111,76 @@ fn main() {
 //=================================================================
 // Utils
 //=================================================================
+/// command line without the program name and the output flags
+pub fn args() -> Vec<String> {
+    split_args().0
+}
+
+fn split_args() -> (Vec<String>, Vec<String>) {
+    let mut args = vec![];
+    let mut output_args = vec![];
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-v" | "--verbose" | "-q" | "--quiet" | "--log-file" => output_args.push(arg),
+            "--log" | "--events" => {
+                output_args.push(arg);
+                output_args.extend(iter.next());
+            }
+            _ if arg.starts_with("--log=") || arg.starts_with("--events=") => output_args.push(arg),
+            _ => args.push(arg),
+        }
+    }
+    (args, output_args)
+}
+
+/// logging and event stream flags
+fn init_output() -> Result<(), String> {
+    let mut filter = LogFilter::new(LevelFilter::Info);
+    if let Ok(spec) = env::var("HL_LOG") {
+        filter.parse(&spec)?;
+    }
+    let mut file = env::var("HL_LOG_FILE").is_ok_and(|value| !value.is_empty() && value != "0");
+    let mut events = env::var("HL_EVENTS").ok().filter(|path| !path.is_empty());
+
+    let mut verbose = 0;
+    let mut output_args = split_args().1.into_iter();
+    while let Some(arg) = output_args.next() {
+        match arg.as_str() {
+            "-v" | "--verbose" => verbose += 1,
+            "-q" | "--quiet" => filter.level = LevelFilter::Warn,
+            "--log-file" => file = true,
+            "--log" => filter.parse(&output_args.next().unwrap_or_default())?,
+            "--events" => events = output_args.next(),
+            arg => match arg.strip_prefix("--events=") {
+                Some(path) => events = Some(path.to_string()),
+                None => filter.parse(arg.trim_start_matches("--log="))?,
+            },
+        }
+    }
+    if let Some(events) = events {
+        event::open(&events)?;
+    }
+    match verbose {
+        0 => {}
+        1 => filter.level = LevelFilter::Debug,
+        _ => filter.level = LevelFilter::Trace,
+    }
+
+    let workspace = env::current_dir().unwrap();
+    logger::init(filter, file.then_some(workspace.as_path()));
+    Ok(())
+}
+
 pub fn list_and_choose(desc: impl Display, list: Vec<impl Display>, default: bool) -> usize {
     loop {
         if !desc.to_string().is_empty() {
-            println!("{}", desc)
+            prompt!("{}", desc)
         };
-        println!("Please choose one");
+        prompt!("Please choose one");
         for (i, command) in list.iter().enumerate() {
             let is_default = if i == 0 && default { "(Default)" } else { "" };
-            println!("{}. {} {}", i, command, is_default);
+            prompt!("{}. {} {}", i, command, is_default);
         }
 
         let input = read_input();
@@ -69,13 +200,13 @@ pub fn list_and_choose(desc: impl Display, list: Vec<impl Display>, default: boo
 
         match choose {
             Some(index) => {
-                println!("Choose `{}`\n{}", list.get(index).unwrap(), BREAK_LINE);
+                prompt!("Choose `{}`\n{}", list.get(index).unwrap(), BREAK_LINE);
                 break index;
             }
             None => {
-                println!("Wrong input");
-                println!("Please retry");
-                println!("{}", BREAK_LINE);
+                prompt!("Wrong input");
+                prompt!("Please retry");
+                prompt!("{}", BREAK_LINE);
             }
         }
     }
@@ -83,10 +214,113 @@ pub fn list_and_choose(desc: impl Display, list: Vec<impl Display>, default: boo
 
 fn read_input() -> String {
     let mut input_text = String::new();
-    print!("> ");
-    io::stdout().flush().unwrap();
-    io::stdin()
+    if event::to_stdout() {
+        eprint!("> ");
+    } else {
+        print!("> ");
+        io::stdout().flush().unwrap();
+    }
+    let read = io::stdin()
         .read_line(&mut input_text)
         .expect("Failed to read line");
+    if read == 0 {
+        //stdin closed, a piped script is done, unless it left a control server running
+        prompt!();
+        control::wait_for_close();
+        process::exit(0);
+    }
     input_text.trim().to_string()
 }
+
+/// pick a folder, with the native dialog when there is a desktop,
+/// otherwise from a typed path
+pub fn read_folder(desc: impl Display) -> Option<PathBuf> {
+    if dialog_available() {
+        return FileDialog::new().set_title(desc.to_string()).pick_folder();
+    }
+    prompt!("{}", desc);
+    read_path()
+}
+
+fn dialog_available() -> bool {
+    if env::var_os("HL_NO_DIALOG").is_some() || env::var_os("SSH_CONNECTION").is_some() {
+        return false;
+    }
+    cfg!(windows)
+        || cfg!(target_os = "macos")
+        || env::var_os("DISPLAY").is_some()
+        || env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// typed folder path, end with `*` to list matches,
+/// an unique partial name completes to its folder
+fn read_path() -> Option<PathBuf> {
+    loop {
+        prompt!("waiting input (folder path, end with `*` to list, empty to cancel)");
+        let input = read_input();
+        if input.is_empty() {
+            prompt!("No Folder picked");
+            return None;
+        }
+
+        let list = input.ends_with('*');
+        let input = expand_home(input.trim_end_matches('*'));
+        if !list && input.is_dir() {
+            return Some(input);
+        }
+
+        let matches = complete_path(&input);
+        match matches.as_slice() {
+            [] => prompt!("Folder `{}` not found", input.display()),
+            [single] if !list => {
+                prompt!("Complete to `{}`", single.display());
+                return Some(single.clone());
+            }
+            matches => {
+                for path in matches {
+                    prompt!("  {}", path.display());
+                }
+            }
+        }
+    }
+}
+
+fn expand_home(input: &str) -> PathBuf {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"));
+    match (input.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest.trim_start_matches(['/', '\\'])),
+        _ => PathBuf::from(input),
+    }
+}
+
+/// folders starting with the last part of `input`
+fn complete_path(input: &Path) -> Vec<PathBuf> {
+    let typed = input.to_string_lossy();
+    let (parent, prefix) = if typed.is_empty() || typed.ends_with(['/', '\\']) {
+        (input.to_path_buf(), String::new())
+    } else {
+        let parent = input.parent().unwrap_or(Path::new(""));
+        let prefix = input.file_name().unwrap_or_default().to_string_lossy();
+        (parent.to_path_buf(), prefix.to_lowercase())
+    };
+    let parent = if parent.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        parent
+    };
+
+    let Ok(entries) = fs::read_dir(&parent) else {
+        return vec![];
+    };
+    let mut matches: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            name.starts_with(&prefix)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    matches.sort();
+    matches
+}
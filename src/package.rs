@@ -0,0 +1,39 @@
+use log::info;
+use sevenz_rust::{Archive, BlockDecoder};
+use std::{fs::File, path::Path};
+
+use crate::BREAK_LINE;
+
+/// variants of a released mod, one folder per variant id
+pub const PACKAGE_FILE: &str = "package.7z";
+
+/// extract the files of `variant` from `package.7z` in `path` into `to`
+pub fn extract(path: &Path, to: &Path, variant: &str) -> Result<(), String> {
+    let zip_path = path.join(PACKAGE_FILE);
+    let mut zip =
+        File::open(&zip_path).map_err(|e| format!("Cannot open {}: {}", zip_path.display(), e))?;
+    let len = zip.metadata().map_err(|e| e.to_string())?.len();
+    let arch = Archive::read(&mut zip, len, &[])
+        .map_err(|e| format!("Cannot read {}: {}", zip_path.display(), e))?;
+    let folder_count = arch.folders.len();
+
+    info!("=Extract={}", BREAK_LINE);
+    for folder_index in 0..folder_count {
+        let forder_dec = BlockDecoder::new(folder_index, &arch, &[], &mut zip);
+        forder_dec
+            .for_each_entries(&mut |entry, reader| {
+                let name = entry.name();
+                if name.starts_with(&format!("{}/", variant)) {
+                    info!("extract {}", entry.name());
+                    let dest = to.join(Path::new(entry.name()).file_name().unwrap());
+                    sevenz_rust::default_entry_extract_fn(entry, reader, &dest)?;
+                } else {
+                    std::io::copy(reader, &mut std::io::sink())?;
+                };
+                Ok(true)
+            })
+            .map_err(|e| format!("Cannot extract {}: {}", zip_path.display(), e))?;
+    }
+    info!("========={}", BREAK_LINE);
+    Ok(())
+}
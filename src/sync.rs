@@ -0,0 +1,202 @@
+use log::{info, trace};
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::event::{self, Event};
+
+/// list of files synced into the install folder, used to remove stale files
+pub const SYNC_MANIFEST: &str = ".hl_sync";
+
+/// result of mirroring a build into an install folder
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub copied: Vec<PathBuf>,
+    pub unchanged: usize,
+    pub removed: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} copied, {} unchanged, {} removed",
+            self.copied.len(),
+            self.unchanged,
+            self.removed.len()
+        )
+    }
+}
+
+/// mirror `from` into `to`, copying only changed files and removing
+/// files a previous sync produced which are no longer built
+pub fn sync_folder(from: &Path, to: &Path) -> Result<SyncReport, String> {
+    if !from.is_dir() {
+        return Err(format!("Build output not found: {}", from.display()));
+    }
+    fs::create_dir_all(to).map_err(|e| format!("Cannot create {}: {}", to.display(), e))?;
+
+    let mut files = BTreeSet::new();
+    list_files(from, Path::new(""), &mut files)
+        .map_err(|e| format!("Cannot read {}: {}", from.display(), e))?;
+
+    let mut report = SyncReport::default();
+    for file in &files {
+        let source = from.join(file);
+        let target = to.join(file);
+        if same_content(&source, &target) {
+            trace!("= {}", target.display());
+            report.unchanged += 1;
+            continue;
+        }
+        info!("* {} -> {}", source.display(), target.display());
+        copy_file(&source, &target)
+            .map_err(|e| format!("Cannot copy {}: {}", source.display(), e))?;
+        event::emit(Event::FileWritten {
+            path: target.clone(),
+        });
+        report.copied.push(file.clone());
+    }
+
+    //only remove files we installed before, the folder may hold other mods
+    let manifest_path = to.join(SYNC_MANIFEST);
+    for file in read_manifest(&manifest_path) {
+        if files.contains(&file) {
+            continue;
+        }
+        let target = to.join(&file);
+        if target.is_file() {
+            info!("- {}", target.display());
+            fs::remove_file(&target)
+                .map_err(|e| format!("Cannot remove {}: {}", target.display(), e))?;
+            remove_empty_parents(&target, to);
+        }
+        report.removed.push(file);
+    }
+
+    let manifest = files
+        .iter()
+        .map(|file| file.to_string_lossy().replace('\\', "/"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(&manifest_path, manifest)
+        .map_err(|e| format!("Cannot write {}: {}", manifest_path.display(), e))?;
+
+    Ok(report)
+}
+
+fn list_files(root: &Path, relative: &Path, files: &mut BTreeSet<PathBuf>) -> io::Result<()> {
+    for entry in root.join(relative).read_dir()? {
+        let entry = entry?;
+        let relative = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            list_files(root, &relative, files)?;
+        } else {
+            files.insert(relative);
+        }
+    }
+    Ok(())
+}
+
+fn read_manifest(path: &Path) -> Vec<PathBuf> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+fn same_content(source: &Path, target: &Path) -> bool {
+    let (Ok(source_meta), Ok(target_meta)) = (fs::metadata(source), fs::metadata(target)) else {
+        return false;
+    };
+    if source_meta.len() != target_meta.len() {
+        return false;
+    }
+    match (fs::read(source), fs::read(target)) {
+        (Ok(source), Ok(target)) => source == target,
+        _ => false,
+    }
+}
+
+/// copy next to the target then rename, so 3dmigoto never reads half a file
+/// and copies work across drives
+fn copy_file(source: &Path, target: &Path) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_name = target.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".hl_tmp");
+    let temp = target.with_file_name(temp_name);
+
+    fs::copy(source, &temp)?;
+    fs::rename(&temp, target).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+fn remove_empty_parents(path: &Path, root: &Path) {
+    let mut current = path.parent();
+    while let Some(folder) = current {
+        if folder == root || !folder.starts_with(root) || fs::remove_dir(folder).is_err() {
+            break;
+        }
+        current = folder.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// empty `build` and `install` folders, unique per test
+    fn folders(name: &str) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("hl_sync_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (from, to) = (root.join("build"), root.join("install"));
+        fs::create_dir_all(from.join("vertex")).unwrap();
+        (from, to)
+    }
+
+    #[test]
+    fn copies_only_changed_files() {
+        let (from, to) = folders("changed");
+        fs::write(from.join("Char.ini"), "a").unwrap();
+        fs::write(from.join("vertex/Char.ib"), "b").unwrap();
+
+        let report = sync_folder(&from, &to).unwrap();
+        assert_eq!(report.copied.len(), 2);
+        assert_eq!(fs::read_to_string(to.join("vertex/Char.ib")).unwrap(), "b");
+
+        fs::write(from.join("Char.ini"), "c").unwrap();
+        let report = sync_folder(&from, &to).unwrap();
+        assert_eq!(report.copied, vec![PathBuf::from("Char.ini")]);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(fs::read_to_string(to.join("Char.ini")).unwrap(), "c");
+    }
+
+    #[test]
+    fn removes_only_files_it_installed() {
+        let (from, to) = folders("removed");
+        fs::write(from.join("Char.ini"), "a").unwrap();
+        fs::write(from.join("vertex/Char.ib"), "b").unwrap();
+        sync_folder(&from, &to).unwrap();
+        fs::write(to.join("Other.ini"), "other mod").unwrap();
+
+        fs::remove_file(from.join("vertex/Char.ib")).unwrap();
+        let report = sync_folder(&from, &to).unwrap();
+        assert_eq!(report.removed, vec![PathBuf::from("vertex/Char.ib")]);
+        assert!(!to.join("vertex").exists());
+        assert!(to.join("Other.ini").is_file());
+        assert!(to.join("Char.ini").is_file());
+    }
+
+    #[test]
+    fn missing_build_is_an_error() {
+        let (from, to) = folders("missing");
+        assert!(sync_folder(&from.join("nothing"), &to).is_err());
+    }
+}
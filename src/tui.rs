@@ -0,0 +1,261 @@
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use log::Level;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListState, Paragraph},
+    Frame, Terminal,
+};
+use std::{
+    io::{self, Stdout},
+    time::Duration,
+};
+
+use crate::{
+    dev::DevSession,
+    logger::{self, LogBuffer},
+};
+
+const KEYS: &str =
+    "↑↓ select  Enter edit  u update  h hot reload  a acknowledge  PgUp/PgDn log  q quit";
+
+/// full screen dev mode, worker output goes to the log pane
+pub fn run(session: &DevSession) -> Result<(), String> {
+    let buffer = LogBuffer::default();
+    logger::capture(Some(buffer.clone()));
+    let result = DevTui::new().and_then(|mut tui| tui.run(session, &buffer));
+    logger::capture(None);
+    result.map_err(|e| format!("Terminal UI failed: {}", e))
+}
+
+struct DevTui {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    selected: ListState,
+    /// lines scrolled up from the end of the log
+    scroll: usize,
+}
+
+impl DevTui {
+    fn new() -> io::Result<DevTui> {
+        enter()?;
+        let terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        Ok(DevTui {
+            terminal,
+            selected: ListState::default().with_selected(Some(0)),
+            scroll: 0,
+        })
+    }
+
+    fn run(&mut self, session: &DevSession, buffer: &LogBuffer) -> io::Result<()> {
+        loop {
+            if session.closed() {
+                return Ok(());
+            }
+            self.terminal
+                .draw(|frame| draw(frame, session, buffer, &mut self.selected, self.scroll))?;
+
+            if !event::poll(Duration::from_millis(200))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            let selected = self.selected.selected().unwrap_or(0);
+            let settings = session.settings().len();
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    session.close();
+                    return Ok(());
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.selected.select(Some(selected.saturating_sub(1)))
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.selected.select(Some((selected + 1).min(settings - 1)))
+                }
+                KeyCode::PageUp => self.scroll += 10,
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(10),
+                KeyCode::End => self.scroll = 0,
+                KeyCode::Char('u') => session.update(),
+                KeyCode::Char('h') => session.toggle_hot_reload(),
+                KeyCode::Char('a') => session.acknowledge(),
+                KeyCode::Enter => {
+                    //settings are edited with their prompts on the normal screen
+                    leave()?;
+                    session.edit(selected);
+                    enter()?;
+                    self.terminal.clear()?;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for DevTui {
+    fn drop(&mut self) {
+        let _ = leave();
+    }
+}
+
+fn enter() -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)
+}
+
+fn leave() -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, cursor::Show)
+}
+
+fn draw(
+    frame: &mut Frame,
+    session: &DevSession,
+    buffer: &LogBuffer,
+    selected: &mut ListState,
+    scroll: usize,
+) {
+    let [main, keys] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.size());
+    let [left, log] =
+        Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(main);
+    let [settings, queue, build] = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(8),
+        Constraint::Length(5),
+    ])
+    .areas(left);
+
+    let title = format!(" Settings [{}] ", session.profile());
+    let list = List::new(session.settings())
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(list, settings, selected);
+
+    draw_queue(frame, session, queue);
+    draw_build(frame, session, build);
+    draw_log(frame, buffer, log, scroll);
+
+    let hot_reload = if session.hot_reload() { "on" } else { "off" };
+    let keys_line = Line::from(vec![
+        Span::styled(
+            format!(" hot reload: {} ", hot_reload),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(KEYS),
+    ]);
+    frame.render_widget(Paragraph::new(keys_line), keys);
+}
+
+fn draw_queue(frame: &mut Frame, session: &DevSession, area: Rect) {
+    let status = session.dds_status.lock().unwrap().clone();
+    let name = |path: &std::path::Path| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    };
+
+    let mut lines = vec![];
+    if status.total > 0 {
+        lines.push(Line::raw(format!(
+            "{}/{} converted",
+            status.done, status.total
+        )));
+    } else {
+        lines.push(Line::raw("Idle"));
+    }
+    for path in &status.running {
+        lines.push(Line::styled(
+            format!("* {}", name(path)),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    for path in &status.queued {
+        lines.push(Line::raw(format!("  {}", name(path))));
+    }
+    for (path, error) in &status.errors {
+        lines.push(Line::styled(
+            format!("! {}: {}", name(path), error),
+            Style::default().fg(Color::Red),
+        ));
+    }
+
+    let block = Block::default().borders(Borders::ALL).title(" DDS Queue ");
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_build(frame: &mut Frame, session: &DevSession, area: Rect) {
+    let lines = match &*session.last_build.lock().unwrap() {
+        None => vec![Line::raw("Not built yet")],
+        Some((finished, result)) => {
+            let ago = finished.elapsed().as_secs();
+            let mut lines = vec![];
+            match &result.error {
+                None => lines.push(Line::styled(
+                    format!(
+                        "{} built in {}ms, {}s ago",
+                        result.name,
+                        result.duration.as_millis(),
+                        ago
+                    ),
+                    Style::default().fg(Color::Green),
+                )),
+                Some(error) => lines.push(Line::styled(
+                    format!(
+                        "Failed {}s ago: {}",
+                        ago,
+                        error.lines().next().unwrap_or_default()
+                    ),
+                    Style::default().fg(Color::Red),
+                )),
+            }
+            for target in &result.targets {
+                lines.push(Line::raw(format!("-> {}", target.display())));
+            }
+            lines
+        }
+    };
+
+    let block = Block::default().borders(Borders::ALL).title(" Last Build ");
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_log(frame: &mut Frame, buffer: &LogBuffer, area: Rect, scroll: usize) {
+    let lines = buffer.lines();
+    let height = area.height.saturating_sub(2) as usize;
+    let end = lines.len().saturating_sub(scroll);
+    let start = end.saturating_sub(height);
+
+    let lines: Vec<Line> = lines[start..end]
+        .iter()
+        .map(|(level, line)| {
+            let style = match level {
+                Level::Error => Style::default().fg(Color::Red),
+                Level::Warn => Style::default().fg(Color::Yellow),
+                _ => Style::default(),
+            };
+            Line::styled(line.clone(), style)
+        })
+        .collect();
+
+    let title = if scroll > 0 {
+        " Log (scrolled) "
+    } else {
+        " Log "
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}